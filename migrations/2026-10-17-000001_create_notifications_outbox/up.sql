@@ -0,0 +1,16 @@
+CREATE TABLE notifications_outbox (
+    id BIGSERIAL PRIMARY KEY,
+    incident_id INTEGER NOT NULL,
+    alerts_id BIGINT NOT NULL,
+    kind INTEGER NOT NULL,
+    channel TEXT NOT NULL,
+    receivers TEXT[] NOT NULL DEFAULT '{}',
+    status INTEGER NOT NULL,
+    attempts INTEGER NOT NULL,
+    last_error TEXT,
+    next_attempt_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX notifications_outbox_pending_idx
+    ON notifications_outbox (next_attempt_at) WHERE status = 0;
@@ -0,0 +1,5 @@
+CREATE TABLE incidents_acks (
+    incident_id INTEGER PRIMARY KEY,
+    acked_by TEXT NOT NULL,
+    acked_at TIMESTAMP NOT NULL
+);
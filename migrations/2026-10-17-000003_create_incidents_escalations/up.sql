@@ -0,0 +1,6 @@
+CREATE TABLE incidents_escalations (
+    incident_id INTEGER PRIMARY KEY,
+    alerts_id BIGINT NOT NULL,
+    tier INTEGER NOT NULL,
+    next_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,16 @@
+CREATE TABLE oncall_schedules (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    timezone TEXT NOT NULL DEFAULT 'UTC',
+    rotation_start TIMESTAMP NOT NULL,
+    rotation_days INTEGER NOT NULL DEFAULT 7,
+    members TEXT[] NOT NULL
+);
+
+CREATE TABLE oncall_overrides (
+    id SERIAL PRIMARY KEY,
+    schedule_id INTEGER NOT NULL REFERENCES oncall_schedules (id) ON DELETE CASCADE,
+    member TEXT NOT NULL,
+    starts_at TIMESTAMP NOT NULL,
+    ends_at TIMESTAMP NOT NULL
+);
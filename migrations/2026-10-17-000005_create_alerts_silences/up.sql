@@ -0,0 +1,11 @@
+CREATE TABLE alerts_silences (
+    id SERIAL PRIMARY KEY,
+    host_uuid TEXT,
+    alert TEXT,
+    cid TEXT,
+    starts_at TIMESTAMP NOT NULL,
+    ends_at TIMESTAMP NOT NULL,
+    comment TEXT NOT NULL,
+    created_by TEXT,
+    CHECK (host_uuid IS NOT NULL OR alert IS NOT NULL OR cid IS NOT NULL)
+);
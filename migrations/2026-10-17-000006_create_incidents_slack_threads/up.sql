@@ -0,0 +1,4 @@
+CREATE TABLE incidents_slack_threads (
+    incident_id INTEGER PRIMARY KEY,
+    ts TEXT NOT NULL
+);
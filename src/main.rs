@@ -10,17 +10,21 @@ use bastion::supervisor::{ActorRestartStrategy, RestartStrategy, SupervisorRef};
 use bastion::Bastion;
 use clap::Parser;
 use diesel::{prelude::PgConnection, r2d2::ConnectionManager};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 use once_cell::sync::Lazy;
 use sproot::{prog, Pool};
 use websockets::ws_handler::WsHandler;
 use websockets::ws_message::{msg_err_handler, msg_ok_database};
 
-use crate::monitoring::monitor::Monitor;
-use crate::notifications::mail;
+use crate::monitoring::{monitor::Monitor, recovery};
+use crate::notifications::{
+    acks, digest, dkim, escalation, oncall, outbox, ratelimit, templates, transport,
+};
 use crate::utils::config::Config;
 
 mod monitoring;
 mod notifications;
+mod schema;
 mod utils;
 mod websockets;
 
@@ -30,6 +34,10 @@ struct Args {
     #[clap(short = 'c', long = "config")]
     config_path: Option<String>,
 
+    /// Render a notification template (incident.html, digest.txt, ...) against sample data and exit
+    #[clap(long = "render-template")]
+    render_template: Option<String>,
+
     #[clap(flatten)]
     verbose: clap_verbosity_flag::Verbosity,
 }
@@ -82,6 +90,18 @@ fn init_pool() -> Pool {
     }
 }
 
+// Migrations of the migrations directory, embedded to check that they were applied before starting
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+
+/// Check that the tables of the notifications exist and are up to date,
+/// they're created by running the migrations (diesel migration run).
+fn check_migrations(pool: &Pool) -> Result<bool, String> {
+    let mut conn = pool.get().map_err(|e| e.to_string())?;
+    conn.has_pending_migration(MIGRATIONS)
+        .map(|pending| !pending)
+        .map_err(|e| e.to_string())
+}
+
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
     let args = Args::parse();
@@ -101,6 +121,30 @@ async fn main() -> std::io::Result<()> {
     // Init logger/tracing
     tracing_subscriber::fmt::init();
 
+    // Check the recover expressions before any incident has to be resolved
+    if let Err(err) = recovery::check_rules(&CONFIG) {
+        error!("Recovery: invalid recovery_rules: {}", err);
+        std::process::exit(1);
+    }
+
+    // Load the custom templates, if any, and check them before any incident occurs
+    if let Err(err) = templates::init() {
+        error!("Templates: invalid template: {}", err);
+        std::process::exit(1);
+    }
+
+    // Only render the template to preview it
+    if let Some(name) = args.render_template {
+        match templates::render_sample(&name) {
+            Ok(content) => println!("{}", content),
+            Err(err) => {
+                error!("Templates: cannot render {}: {}", name, err);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
     // Initialize the connections'pool (r2d2 sync)
     let pool = init_pool();
 
@@ -117,8 +161,65 @@ async fn main() -> std::io::Result<()> {
         pool: &pool,
     };
 
-    // Check if the SMTP server host is "ok"
-    mail::test_smtp_transport();
+    // Check if the mail transport is "ok"
+    transport::init();
+    dkim::init();
+
+    // The outbox, acks, escalations, ... tables are created by the migrations
+    match check_migrations(&pool) {
+        Ok(true) => {}
+        Ok(false) => {
+            error!("Migrations: the database is not up to date, run `diesel migration run`");
+            std::process::exit(1);
+        }
+        Err(err) => {
+            error!("Migrations: cannot check the database: {}", err);
+            std::process::exit(1);
+        }
+    }
+
+    // Retry the notifications of the digests lost with the previous run, and the failed ones
+    if let Err(err) = outbox::init(&pool) {
+        error!(
+            "Outbox: cannot recover the notifications of the digests: {}",
+            err
+        );
+        std::process::exit(1);
+    }
+    outbox::start_worker(pool.clone());
+
+    // Start handling the ack links
+    if let Some(addr) = &CONFIG.ack_listen {
+        if !acks::enabled() {
+            error!("Acks: ack_listen requires the ack_url and ack_secret");
+            std::process::exit(1);
+        }
+        acks::start_server(addr, pool.clone());
+    }
+
+    // Load the on-call schedules and keep them up to date
+    if let Err(err) = oncall::init(&pool) {
+        error!("On-call: cannot load the schedules: {}", err);
+        std::process::exit(1);
+    }
+    oncall::start_worker(pool.clone());
+
+    // Start notifying the next tiers of the escalation policies
+    if let Err(err) = escalation::check_policies(&CONFIG) {
+        error!("Escalation: invalid escalation_policies: {}", err);
+        std::process::exit(1);
+    }
+    escalation::start_worker(pool.clone());
+
+    // Start sending the digests if the mails are aggregated
+    if let Some(window) = CONFIG.digest_window {
+        digest::start_worker(Duration::from_secs(window), pool.clone());
+    }
+
+    // Start sending the summaries of the suppressed notifications if they're rate limited
+    if CONFIG.rate_limit_receiver.is_some() || CONFIG.rate_limit_channel.is_some() {
+        ratelimit::start_worker();
+    }
 
     // Build the default monitor struct
     let monitor = Monitor::default(&pool);
@@ -6,8 +6,8 @@ use sproot::{
     ConnType,
 };
 
-use super::{alerts::WholeAlert, IncidentStatus, Severity};
-use crate::notifications::mail;
+use super::{alerts::WholeAlert, pending, recovery, silences, IncidentStatus, Severity};
+use crate::notifications::{self, EventKind};
 
 /// Determine if we are in a Warn or Crit level of incidents
 fn check_threshold(walert: &WholeAlert, result: &str) -> (bool, bool) {
@@ -32,6 +32,10 @@ fn check_threshold(walert: &WholeAlert, result: &str) -> (bool, bool) {
 /// This function is the core of the monitoring, this is where we:
 /// - Execute the query and get the result
 /// - Evaluate if we need to trigger an incidents or not
+///
+/// While the alert is silenced, the incidents are still updated but no new one is
+/// created and nothing is notified. A new incident is only created once the threshold
+/// was breached for as long as the PendingRule of the alert requires.
 pub fn execute_analysis(walert: &WholeAlert, conn: &mut ConnType) {
     info!(
         "[{}] Executing {} analysis for {:.6}",
@@ -56,6 +60,20 @@ pub fn execute_analysis(walert: &WholeAlert, conn: &mut ConnType) {
     // This call to check_threshold panics if the evals fails
     let (should_warn, should_crit) = check_threshold(walert, &result);
 
+    // Still evaluated above, but nothing is notified during a maintenance
+    let silence = silences::find(conn, &walert.inner);
+    if let Some(silence) = &silence {
+        debug!(
+            "[{}] alert {} for host_uuid {:.6} silenced until {} UTC by {} ({})",
+            walert.inner.id,
+            walert.inner.name,
+            walert.inner.host_uuid,
+            silence.ends_at,
+            silence.created_by.as_deref().unwrap_or("unknown"),
+            silence.comment
+        );
+    }
+
     // Check if an active incident already exist for this alarm (err == not found).
     let prev_incident: Option<Incidents> = match Incidents::find_active(conn, walert.inner.id) {
         Ok(res) => Some(res),
@@ -64,8 +82,29 @@ pub fn execute_analysis(walert: &WholeAlert, conn: &mut ConnType) {
 
     // Assert that we do not create an incident for nothing
     if !(should_warn || should_crit) {
+        // A spike shorter than the pending duration does not count for the next one
+        pending::clear(walert.inner.id);
         // Check if an incident was active
         if let Some(prev_incident) = prev_incident {
+            // Keep the incident active until the value really recovered (hysteresis)
+            if !recovery::is_recovered(&walert.inner, &result) {
+                trace!(
+                    ">[{}] Below the thresholds but not recovered yet",
+                    walert.inner.id
+                );
+                // TODO - Handle error
+                Incidents::update_and_get(
+                    conn,
+                    prev_incident.id,
+                    &IncidentsDTOUpdate {
+                        result: Some(result),
+                        updated_at: Some(Utc::now().naive_local()),
+                        ..Default::default()
+                    },
+                )
+                .expect("Failed to update the incidents");
+                return;
+            }
             info!(
                 ">[{}] We need to resolve the previous incident however",
                 walert.inner.id
@@ -82,10 +121,11 @@ pub fn execute_analysis(walert: &WholeAlert, conn: &mut ConnType) {
                 },
             )
             .expect("Failed to update (resolve) the incidents");
-            let alert =
-                Alerts::get_specific(conn, incident.alerts_id).expect("Failed to get the alert");
-            // TODO - Handle error
-            mail::send_information_mail(&alert, &incident, false);
+            if silence.is_none() {
+                let alert = Alerts::get_specific(conn, incident.alerts_id)
+                    .expect("Failed to get the alert");
+                notifications::dispatch(conn, EventKind::Resolved, &alert, &incident);
+            }
         }
         return;
     }
@@ -135,13 +175,24 @@ pub fn execute_analysis(walert: &WholeAlert, conn: &mut ConnType) {
             )
             .expect("Failed to update the incidents");
             // We should_alert if the prev severity is lower than the current one
-            if should_alert {
+            if should_alert && silence.is_none() {
                 let alert = Alerts::get_specific(conn, incident.alerts_id)
                     .expect("Failed to get the alert");
-                // TODO - Handle error
-                mail::send_information_mail(&alert, &incident, true);
+                notifications::dispatch(conn, EventKind::Escalated, &alert, &incident);
             }
         }
+        None if silence.is_some() => {
+            info!(
+                ">[{}] No incident created, the alert {} is silenced",
+                walert.inner.id, walert.inner.name
+            );
+        }
+        None if !pending::should_fire(&walert.inner) => {
+            info!(
+                ">[{}] Incident pending, the alert {} must breach its threshold longer",
+                walert.inner.id, walert.inner.name
+            );
+        }
         None => {
             info!(
                 ">[{}] Create a new incident based on the current values",
@@ -167,8 +218,7 @@ pub fn execute_analysis(walert: &WholeAlert, conn: &mut ConnType) {
             .expect("Failed to insert a new incident");
             let alert =
                 Alerts::get_specific(conn, incident.alerts_id).expect("Failed to get the alert");
-            // TODO - Handle error
-            mail::send_information_mail(&alert, &incident, false);
+            notifications::dispatch(conn, EventKind::New, &alert, &incident);
         }
     }
 }
@@ -1,11 +1,19 @@
 pub mod alerts;
 pub mod analysis;
 pub mod monitor;
+pub mod pending;
+pub mod recovery;
+pub mod silences;
 
 /// Enum representing the current Status of the Incidents
+///
+/// Acknowledged incidents are still Active in the incidents table, their ack being
+/// stored by the notifications (see acks).
+#[derive(Debug, PartialEq)]
 pub enum IncidentStatus {
     Active,
     Resolved,
+    Acknowledged,
 }
 
 impl std::fmt::Display for IncidentStatus {
@@ -17,6 +25,9 @@ impl std::fmt::Display for IncidentStatus {
             IncidentStatus::Resolved => {
                 write!(f, "Resolved")
             }
+            IncidentStatus::Acknowledged => {
+                write!(f, "Acknowledged")
+            }
         }
     }
 }
@@ -31,7 +42,7 @@ impl From<i32> for IncidentStatus {
 }
 
 /// Enum representing the Severity of the Incidents
-#[derive(Clone)]
+#[derive(Clone, Copy)]
 pub enum Severity {
     Warning,
     Critical,
@@ -0,0 +1,158 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+use sproot::models::Alerts;
+
+use crate::{utils::config::regex_deser, CONFIG};
+
+// Lazy static of the alerts breaching their threshold without incident yet, by alert id
+static PENDING: Lazy<Mutex<HashMap<i64, Pending>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Structure representing how long an alert must breach its threshold before
+/// its incident is created, as defined in the Config.
+///
+/// The first matching rule is used, `alert` being an (anchored) regex on the name of the alert.
+/// When both `for` and `evaluations` are set, both must be reached.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PendingRule {
+    #[serde(default, deserialize_with = "regex_deser")]
+    pub alert: Option<Regex>,
+    pub host_uuid: Option<String>,
+    /// Seconds during which the threshold must be breached
+    #[serde(rename = "for")]
+    pub for_secs: Option<u64>,
+    /// Number of consecutive evaluations breaching the threshold
+    pub evaluations: Option<u32>,
+}
+
+impl PendingRule {
+    fn matches(&self, alert: &Alerts) -> bool {
+        self.alert
+            .as_ref()
+            .is_none_or(|re| re.is_match(&alert.name))
+            && self
+                .host_uuid
+                .as_ref()
+                .is_none_or(|uuid| uuid == &alert.host_uuid)
+    }
+}
+
+/// Structure representing an alert breaching its threshold since a while
+struct Pending {
+    since: Instant,
+    evaluations: u32,
+}
+
+/// Check if the incident of the alert breaching its threshold must be created,
+/// or if it is still pending.
+pub fn should_fire(alert: &Alerts) -> bool {
+    should_fire_at(
+        &CONFIG.pending_rules,
+        &mut PENDING.lock().unwrap(),
+        alert,
+        Instant::now(),
+    )
+}
+
+fn should_fire_at(
+    rules: &[PendingRule],
+    pending: &mut HashMap<i64, Pending>,
+    alert: &Alerts,
+    now: Instant,
+) -> bool {
+    let rule = match rules.iter().find(|r| r.matches(alert)) {
+        Some(rule) => rule,
+        None => return true,
+    };
+
+    let entry = pending.entry(alert.id).or_insert_with(|| Pending {
+        since: now,
+        evaluations: 0,
+    });
+    entry.evaluations += 1;
+
+    let fires = rule
+        .for_secs
+        .is_none_or(|secs| now.duration_since(entry.since) >= Duration::from_secs(secs))
+        && rule.evaluations.is_none_or(|n| entry.evaluations >= n);
+    if fires {
+        pending.remove(&alert.id);
+    }
+    fires
+}
+
+/// Forget the pending state of the alert, its threshold not being breached anymore
+pub fn clear(alert_id: i64) {
+    PENDING.lock().unwrap().remove(&alert_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        time::{Duration, Instant},
+    };
+
+    use super::{should_fire_at, PendingRule};
+    use crate::notifications::mock;
+
+    fn rule(for_secs: Option<u64>, evaluations: Option<u32>) -> PendingRule {
+        PendingRule {
+            alert: None,
+            host_uuid: None,
+            for_secs,
+            evaluations,
+        }
+    }
+
+    #[test]
+    fn without_rule() {
+        let alert = mock::alert();
+        let mut pending = HashMap::new();
+        assert!(should_fire_at(&[], &mut pending, &alert, Instant::now()));
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn for_and_evaluations() {
+        // Fires after 3 evaluations which lasted at least 2 minutes
+        let rules = [rule(Some(120), Some(3))];
+        let alert = mock::alert();
+        let mut pending = HashMap::new();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert!(!should_fire_at(&rules, &mut pending, &alert, at(0)));
+        // The duration is reached but not the evaluations
+        assert!(!should_fire_at(&rules, &mut pending, &alert, at(150)));
+        assert!(should_fire_at(&rules, &mut pending, &alert, at(180)));
+        // Firing starts a new pending period
+        assert!(pending.is_empty());
+
+        // The evaluations are reached but not the duration
+        for secs in [200, 210, 220, 230] {
+            assert!(!should_fire_at(&rules, &mut pending, &alert, at(secs)));
+        }
+        assert!(should_fire_at(&rules, &mut pending, &alert, at(320)));
+    }
+
+    #[test]
+    fn cleared_when_healthy() {
+        let rules = [rule(None, Some(2))];
+        let alert = mock::alert();
+        let mut pending = HashMap::new();
+        let now = Instant::now();
+
+        assert!(!should_fire_at(&rules, &mut pending, &alert, now));
+        // An evaluation within the thresholds clears the pending state, as in clear
+        pending.remove(&alert.id);
+        assert!(!should_fire_at(&rules, &mut pending, &alert, now));
+        assert!(should_fire_at(&rules, &mut pending, &alert, now));
+    }
+}
@@ -0,0 +1,115 @@
+use evalexpr::eval_boolean;
+use regex::Regex;
+use serde::Deserialize;
+use sproot::models::Alerts;
+
+use crate::{
+    utils::config::{regex_deser, Config},
+    CONFIG,
+};
+
+/// Structure representing the expression an alert must meet for its incident
+/// to be resolved, as defined in the Config.
+///
+/// Without it, the incident is resolved as soon as neither `warn` nor `crit` hold,
+/// so that a value hovering around the threshold keeps opening and resolving incidents.
+/// The first matching rule is used, `alert` being an (anchored) regex on the name of the alert.
+#[derive(Debug, Deserialize, Clone)]
+pub struct RecoveryRule {
+    #[serde(default, deserialize_with = "regex_deser")]
+    pub alert: Option<Regex>,
+    pub host_uuid: Option<String>,
+    /// Expression using $this like warn and crit, e.g. "$this < 70"
+    pub recover: String,
+}
+
+impl RecoveryRule {
+    fn matches(&self, alert: &Alerts) -> bool {
+        self.alert
+            .as_ref()
+            .is_none_or(|re| re.is_match(&alert.name))
+            && self
+                .host_uuid
+                .as_ref()
+                .is_none_or(|uuid| uuid == &alert.host_uuid)
+    }
+}
+
+/// Check that the recover expressions are valid boolean expressions
+pub fn check_rules(config: &Config) -> Result<(), String> {
+    for rule in &config.recovery_rules {
+        eval_boolean(&rule.recover.replace("$this", "0"))
+            .map_err(|e| format!("invalid expression \"{}\": {}", rule.recover, e))?;
+    }
+    Ok(())
+}
+
+/// Check if the incident of the alert, whose warn and crit don't hold anymore, can be resolved
+pub fn is_recovered(alert: &Alerts, result: &str) -> bool {
+    is_recovered_with(&CONFIG.recovery_rules, alert, result)
+}
+
+fn is_recovered_with(rules: &[RecoveryRule], alert: &Alerts, result: &str) -> bool {
+    let rule = match rules.iter().find(|r| r.matches(alert)) {
+        Some(rule) => rule,
+        None => return true,
+    };
+
+    eval_boolean(&rule.recover.replace("$this", result)).unwrap_or_else(|e| {
+        error!(
+            "[{}] alert {} for host_uuid {:.6} failed to parse the String to an expression (recover: {}): {}",
+            alert.id, alert.name, alert.host_uuid, rule.recover, e
+        );
+        // Resolving is safer than keeping an incident nobody can close
+        true
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::{is_recovered_with, RecoveryRule};
+    use crate::notifications::mock;
+
+    fn rule(alert: Option<&str>, recover: &str) -> RecoveryRule {
+        RecoveryRule {
+            alert: alert.map(|re| Regex::new(re).unwrap()),
+            host_uuid: None,
+            recover: recover.to_owned(),
+        }
+    }
+
+    #[test]
+    fn recover_expression() {
+        let alert = mock::alert();
+        let rules = [rule(None, "$this < 70")];
+
+        assert!(is_recovered_with(&rules, &alert, "65.2"));
+        // Still above the recover threshold, so the incident stays open
+        assert!(!is_recovered_with(&rules, &alert, "72"));
+        // Without a matching rule, the incident is resolved as soon as warn and crit don't hold
+        assert!(is_recovered_with(&[], &alert, "72"));
+        let other = [rule(Some("^disk$"), "$this < 70")];
+        assert!(is_recovered_with(&other, &alert, "72"));
+    }
+
+    #[test]
+    fn failing_expression() {
+        let alert = mock::alert();
+
+        // The expression is invalid, or does not evaluate to a boolean with this result
+        for (recover, result) in [
+            ("$this <", "72"),
+            ("$this + 1", "72"),
+            ("$this < 70", "not a number"),
+        ] {
+            assert!(
+                is_recovered_with(&[rule(None, recover)], &alert, result),
+                "{} with {}",
+                recover,
+                result
+            );
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use chrono::{prelude::Utc, NaiveDateTime};
+use diesel::prelude::*;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sproot::{models::Alerts, ConnType};
+
+use crate::schema::alerts_silences;
+
+/// Compiled alert patterns of the silences, by id, with the pattern they were
+/// compiled from (None if it's invalid)
+type Patterns = HashMap<i32, (String, Option<Regex>)>;
+
+// Lazy static of the compiled alert patterns of the active silences
+static PATTERNS: Lazy<Mutex<Patterns>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Structure representing a silence (maintenance window) of the alerts.
+///
+/// Every scope which is set must match, `alert` being an (anchored) regex on the
+/// name of the alert. While it's active the alerts are still evaluated, but no
+/// incident is created nor notified.
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = alerts_silences)]
+pub struct Silence {
+    pub id: i32,
+    pub host_uuid: Option<String>,
+    pub alert: Option<String>,
+    pub cid: Option<String>,
+    pub ends_at: NaiveDateTime,
+    pub comment: String,
+    pub created_by: Option<String>,
+}
+
+impl Silence {
+    fn matches(&self, alert: &Alerts, patterns: &mut Patterns) -> bool {
+        self.host_uuid
+            .as_ref()
+            .is_none_or(|uuid| uuid == &alert.host_uuid)
+            && self
+                .cid
+                .as_ref()
+                .is_none_or(|cid| cid.eq_ignore_ascii_case(&alert.cid.to_string()))
+            && self.alert.as_ref().is_none_or(|pattern| {
+                self.compiled(pattern, patterns)
+                    .is_some_and(|re| re.is_match(&alert.name))
+            })
+    }
+
+    /// Get the regex of the pattern, only compiled when the silence is first seen or its pattern changed
+    fn compiled<'a>(&self, pattern: &str, patterns: &'a mut Patterns) -> Option<&'a Regex> {
+        if patterns
+            .get(&self.id)
+            .is_none_or(|(compiled, _)| compiled != pattern)
+        {
+            // Anchor the pattern so that "disk" does not match "disk_io"
+            let re = match Regex::new(&format!("^(?:{})$", pattern)) {
+                Ok(re) => Some(re),
+                Err(err) => {
+                    error!(
+                        "Silences: invalid alert pattern of silence {}: {}",
+                        self.id, err
+                    );
+                    None
+                }
+            };
+            patterns.insert(self.id, (pattern.to_owned(), re));
+        }
+        patterns[&self.id].1.as_ref()
+    }
+}
+
+/// Get the active silence matching the alert, if any
+pub fn find(conn: &mut ConnType, alert: &Alerts) -> Option<Silence> {
+    let now = Utc::now().naive_utc();
+    match alerts_silences::table
+        .filter(alerts_silences::starts_at.le(now))
+        .filter(alerts_silences::ends_at.gt(now))
+        .order(alerts_silences::id)
+        .select(Silence::as_select())
+        .load(conn)
+    {
+        Ok(silences) => {
+            let mut patterns = PATTERNS.lock().unwrap();
+            // Forget the patterns of the silences which ended or were removed
+            patterns.retain(|id, _| silences.iter().any(|s| s.id == *id));
+            silences
+                .into_iter()
+                .find(|s| s.matches(alert, &mut patterns))
+        }
+        Err(err) => {
+            error!("Silences: could not get the active silences: {}", err);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::{Patterns, Silence};
+    use crate::notifications::mock;
+
+    fn silence(alert: &str) -> Silence {
+        Silence {
+            id: 1,
+            host_uuid: None,
+            alert: Some(alert.to_owned()),
+            cid: None,
+            ends_at: NaiveDateTime::default(),
+            comment: String::new(),
+            created_by: None,
+        }
+    }
+
+    #[test]
+    fn patterns() {
+        // The name of the mock alert is "cpu & <load>"
+        let alert = mock::alert();
+        let mut patterns = Patterns::new();
+
+        assert!(silence("cpu.*").matches(&alert, &mut patterns));
+        // Anchored, so that a part of the name does not match
+        assert!(!silence("cpu").matches(&alert, &mut patterns));
+        // The pattern changed since it was compiled
+        assert_eq!(patterns[&1].0, "cpu");
+        assert!(!silence("(").matches(&alert, &mut patterns));
+        assert!(patterns[&1].1.is_none());
+    }
+}
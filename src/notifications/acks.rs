@@ -0,0 +1,365 @@
+use std::{io::Read, thread};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{prelude::Utc, NaiveDateTime};
+use diesel::prelude::*;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sproot::{
+    apierrors::ApiError,
+    models::{Alerts, BaseCrud, Incidents},
+    ConnType, Pool,
+};
+use tiny_http::{Header, Method, Request, Response, Server};
+use url::form_urlencoded;
+
+use super::{
+    html_escape,
+    i18n::{fill, Lang},
+};
+use crate::{monitoring::IncidentStatus, schema::incidents_acks, CONFIG};
+
+/// Maximum size of the body of the ack form
+const MAX_FORM_SIZE: u64 = 4096;
+
+/// Structure representing who acknowledged an incident, and when
+#[derive(Queryable, Selectable, Insertable, Debug)]
+#[diesel(table_name = incidents_acks)]
+pub struct Ack {
+    pub incident_id: i32,
+    pub acked_by: String,
+    pub acked_at: NaiveDateTime,
+}
+
+/// Check if the incidents can be acknowledged, the links needing the ack_url and ack_secret
+pub fn enabled() -> bool {
+    CONFIG.ack_url.is_some() && CONFIG.ack_secret.is_some()
+}
+
+/// Get the acknowledgement of the incident, if any
+pub fn find(conn: &mut ConnType, incident_id: i32) -> Option<Ack> {
+    match incidents_acks::table
+        .find(incident_id)
+        .select(Ack::as_select())
+        .first(conn)
+        .optional()
+    {
+        Ok(ack) => ack,
+        Err(err) => {
+            error!(
+                "Acks: could not get the ack of incident {}: {}",
+                incident_id, err
+            );
+            None
+        }
+    }
+}
+
+/// Status of the incident, the acknowledgement being stored apart from the incidents table
+pub fn status(conn: &mut ConnType, incident: &Incidents) -> IncidentStatus {
+    match IncidentStatus::from(incident.status) {
+        IncidentStatus::Active if find(conn, incident.id).is_some() => IncidentStatus::Acknowledged,
+        status => status,
+    }
+}
+
+/// Acknowledge the incident, keeping the first ack if it already was
+fn acknowledge(conn: &mut ConnType, incident_id: i32, by: &str) -> Result<Ack, ApiError> {
+    diesel::insert_into(incidents_acks::table)
+        .values(&Ack {
+            incident_id,
+            acked_by: by.to_owned(),
+            acked_at: Utc::now().naive_utc(),
+        })
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+
+    Ok(incidents_acks::table
+        .find(incident_id)
+        .select(Ack::as_select())
+        .first(conn)?)
+}
+
+/// Signed link acknowledging the incident, valid for ack_link_validity seconds.
+///
+/// The receiver only prefills the form, as the same mail can be sent to several persons.
+pub fn link(incident_id: i32, receiver: Option<&str>) -> Option<String> {
+    let base = CONFIG.ack_url.as_ref()?;
+    let expires = Utc::now().timestamp() + CONFIG.ack_link_validity as i64;
+    let token = URL_SAFE_NO_PAD.encode(format!("{}:{}", incident_id, expires));
+
+    let mut query = form_urlencoded::Serializer::new(String::new());
+    query
+        .append_pair("t", &token)
+        .append_pair("s", &sign(&token)?);
+    if let Some(receiver) = receiver {
+        query.append_pair("by", receiver);
+    }
+    Some(format!(
+        "{}/ack?{}",
+        base.trim_end_matches('/'),
+        query.finish()
+    ))
+}
+
+/// Signature (HMAC-SHA256 with the ack_secret) of the token
+fn sign(token: &str) -> Option<String> {
+    let secret = CONFIG.ack_secret.as_ref()?;
+    Some(
+        URL_SAFE_NO_PAD.encode(
+            hmac_sha256(secret.as_bytes(), token.as_bytes())
+                .finalize()
+                .into_bytes(),
+        ),
+    )
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Hmac<Sha256> {
+    // HMAC accepts keys of any size, hashing the ones longer than a block
+    Hmac::<Sha256>::new_from_slice(key)
+        .expect("HMAC accepts keys of any size")
+        .chain_update(message)
+}
+
+/// Get the incident id of the token if its signature is valid and it did not expire
+fn verify(token: &str, signature: &str) -> Option<i32> {
+    let secret = CONFIG.ack_secret.as_ref()?;
+    let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+    // Compared in constant time so that the signature can't be guessed byte after byte
+    hmac_sha256(secret.as_bytes(), token.as_bytes())
+        .verify_slice(&signature)
+        .ok()?;
+
+    let token = String::from_utf8(URL_SAFE_NO_PAD.decode(token).ok()?).ok()?;
+    let (incident_id, expires) = token.split_once(':')?;
+    if expires.parse::<i64>().ok()? < Utc::now().timestamp() {
+        return None;
+    }
+    incident_id.parse().ok()
+}
+
+/// Start the HTTP server handling the ack links on ack_listen.
+///
+/// tiny_http blocks while waiting for the requests, so that it gets its own
+/// thread instead of starving the executor of the Bastion children.
+pub fn start_server(addr: &str, pool: Pool) {
+    let server = match Server::http(addr) {
+        Ok(server) => server,
+        Err(err) => {
+            error!("Acks: cannot listen on {}: {}", addr, err);
+            std::process::exit(1);
+        }
+    };
+    info!("Acks: listening on {}", addr);
+
+    if let Err(err) = thread::Builder::new()
+        .name("acks".to_owned())
+        .spawn(move || {
+            for request in server.incoming_requests() {
+                handle(&pool, request);
+            }
+        })
+    {
+        error!("Acks: cannot start the server thread: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn handle(pool: &Pool, mut request: Request) {
+    let url = request.url().to_owned();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let params: Vec<(String, String)> = form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect();
+    let param = |name: &str| {
+        params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.to_owned())
+    };
+
+    let (code, body) = match (request.method(), path) {
+        (Method::Get, "/ack") | (Method::Post, "/ack") => {
+            let incident_id = match (param("t"), param("s")) {
+                (Some(token), Some(signature)) => verify(&token, &signature),
+                _ => None,
+            };
+            // The form is only submitted by POST so that the links prefetched by
+            // the mail scanners do not acknowledge the incident on their own.
+            let by = if *request.method() == Method::Post {
+                let mut form = String::new();
+                let _ = request
+                    .as_reader()
+                    .take(MAX_FORM_SIZE)
+                    .read_to_string(&mut form);
+                form_urlencoded::parse(form.as_bytes())
+                    .find(|(key, _)| key == "by")
+                    .map(|(_, value)| value.trim().to_owned())
+                    .filter(|by| !by.is_empty())
+            } else {
+                None
+            };
+
+            match (incident_id, pool.get()) {
+                (None, _) => (403, message(Lang::default().catalog().ack_invalid)),
+                (Some(incident_id), Ok(mut conn)) => {
+                    ack_page(&mut conn, incident_id, by, param("by"))
+                }
+                (_, Err(err)) => {
+                    error!("Acks: cannot get a connection: {}", err);
+                    (500, message("Internal error"))
+                }
+            }
+        }
+        _ => (404, message("Not found")),
+    };
+
+    // SAFETY: the header is valid ascii
+    let header = Header::from_bytes("Content-Type", "text/html; charset=utf-8").unwrap();
+    if let Err(err) = request.respond(
+        Response::from_string(body)
+            .with_status_code(code)
+            .with_header(header),
+    ) {
+        warn!("Acks: could not respond: {}", err);
+    }
+}
+
+/// Confirmation form of the ack, or its result once submitted with who acknowledged it
+fn ack_page(
+    conn: &mut ConnType,
+    incident_id: i32,
+    by: Option<String>,
+    hint: Option<String>,
+) -> (u16, String) {
+    let lang = Lang::default();
+    let catalog = lang.catalog();
+    let incident = match Incidents::get_specific(conn, incident_id) {
+        Ok(incident) => incident,
+        Err(_) => return (404, message(catalog.ack_invalid)),
+    };
+    let alert_name = Alerts::get_specific(conn, incident.alerts_id)
+        .map(|alert| alert.name)
+        .unwrap_or_default();
+    let values = [
+        ("alert", alert_name.as_str()),
+        ("hostname", incident.hostname.as_str()),
+    ];
+
+    let ack = match (status(conn, &incident), by) {
+        (IncidentStatus::Resolved, _) => {
+            return (200, message(&fill(catalog.ack_resolved, &values)))
+        }
+        (IncidentStatus::Acknowledged, _) => find(conn, incident_id),
+        (IncidentStatus::Active, Some(by)) => match acknowledge(conn, incident_id, &by) {
+            Ok(ack) => {
+                info!(
+                    "Acks: incident {} acknowledged by {}",
+                    incident_id, ack.acked_by
+                );
+                Some(ack)
+            }
+            Err(err) => {
+                error!(
+                    "Acks: could not acknowledge incident {}: {}",
+                    incident_id, err
+                );
+                return (500, message("Internal error"));
+            }
+        },
+        (IncidentStatus::Active, None) => None,
+    };
+
+    match ack {
+        Some(ack) => (
+            200,
+            message(&fill(
+                catalog.ack_done,
+                &[
+                    values[0],
+                    values[1],
+                    ("by", &ack.acked_by),
+                    ("date", &lang.format_date(ack.acked_at)),
+                ],
+            )),
+        ),
+        None => (
+            200,
+            page(&format!(
+                "<h1>{}</h1><p>{}</p><form method=\"post\"><input name=\"by\" required placeholder=\"{}\" value=\"{}\"> <button type=\"submit\">{}</button></form>",
+                html_escape(catalog.ack_title),
+                html_escape(&fill(catalog.ack_confirm, &values)),
+                html_escape(catalog.ack_by),
+                html_escape(&hint.unwrap_or_default()),
+                html_escape(catalog.acknowledge),
+            )),
+        ),
+    }
+}
+
+fn page(html: &str) -> String {
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><meta name=\"viewport\" content=\"width=device-width,initial-scale=1\"><title>Speculare</title></head><body style=\"background-color:#121212;color:#c5c8cb;font-family:Montserrat,'Trebuchet MS',Tahoma,sans-serif;max-width:550px;margin:60px auto;padding:0 25px\">{}</body></html>",
+        html
+    )
+}
+
+/// Page only displaying the text
+fn message(text: &str) -> String {
+    page(&format!("<p>{}</p>", html_escape(text)))
+}
+
+#[cfg(test)]
+mod tests {
+    use hmac::Mac;
+
+    use super::hmac_sha256;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    // Test cases of RFC 4231 for HMAC-SHA-256 (the 5th one being truncated is left out)
+    #[test]
+    fn rfc4231() {
+        let cases: [(Vec<u8>, &[u8], &str); 6] = [
+            (
+                vec![0x0b; 20],
+                b"Hi There",
+                "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+            ),
+            (
+                b"Jefe".to_vec(),
+                b"what do ya want for nothing?",
+                "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            ),
+            (
+                vec![0xaa; 20],
+                &[0xdd; 50],
+                "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe",
+            ),
+            (
+                (0x01..=0x19).collect(),
+                &[0xcd; 50],
+                "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b",
+            ),
+            (
+                vec![0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First",
+                "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+            ),
+            (
+                vec![0xaa; 131],
+                b"This is a test using a larger than block-size key and a larger than block-size data. The key needs to be hashed before being used by the HMAC algorithm.",
+                "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2",
+            ),
+        ];
+
+        for (key, data, expected) in cases.iter() {
+            assert_eq!(
+                &hex(&hmac_sha256(key, data).finalize().into_bytes()),
+                expected
+            );
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use chrono::{prelude::Utc, Datelike, NaiveDateTime, NaiveTime, TimeZone, Weekday};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+use crate::{
+    monitoring::Severity,
+    utils::config::{opt_tz_deser, time_deser, weekdays_deser},
+    CONFIG,
+};
+
+/// Structure representing when the incidents of a severity can be delivered, as defined in the Config.
+///
+/// Outside of its windows, the notifications of the severity are held in the outbox
+/// and sent when the next window opens. A severity without windows is delivered at any time.
+#[derive(Debug, Deserialize, Clone)]
+pub struct DeliveryWindow {
+    /// Severity (Warning/Critical) restricted by the window, every one if not set
+    pub severity: Option<String>,
+    /// Days of the week on which the window opens, every day if not set
+    #[serde(default, deserialize_with = "weekdays_deser")]
+    pub days: Option<Vec<Weekday>>,
+    #[serde(deserialize_with = "time_deser")]
+    pub start: NaiveTime,
+    #[serde(deserialize_with = "time_deser")]
+    pub end: NaiveTime,
+    /// Timezone of the start and end, the timezone of the Config if not set
+    #[serde(default, deserialize_with = "opt_tz_deser")]
+    pub timezone: Option<Tz>,
+}
+
+impl DeliveryWindow {
+    fn applies_to(&self, severity: Severity) -> bool {
+        self.severity
+            .as_ref()
+            .is_none_or(|s| s.eq_ignore_ascii_case(&severity.to_string()))
+    }
+
+    fn timezone(&self) -> Tz {
+        self.timezone.unwrap_or(CONFIG.timezone)
+    }
+
+    fn opens_on(&self, day: Weekday) -> bool {
+        self.days.as_ref().is_none_or(|days| days.contains(&day))
+    }
+
+    /// Check if the window is open at the (UTC) date.
+    ///
+    /// A window over midnight belongs to the day it opened, and one whose
+    /// start and end are equal lasts 24 hours.
+    fn is_open(&self, now: NaiveDateTime) -> bool {
+        let local = Utc
+            .from_utc_datetime(&now)
+            .with_timezone(&self.timezone())
+            .naive_local();
+        let (day, time) = (local.weekday(), local.time());
+
+        if self.start < self.end {
+            self.start <= time && time < self.end && self.opens_on(day)
+        } else if time >= self.start {
+            self.opens_on(day)
+        } else {
+            time < self.end && self.opens_on(day.pred())
+        }
+    }
+
+    /// Next (UTC) date at which the window opens, None if it never does
+    fn next_opening(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let tz = self.timezone();
+        let today = Utc.from_utc_datetime(&now).with_timezone(&tz).date_naive();
+
+        (0..=7)
+            .map(|days| today + chrono::Duration::days(days))
+            .filter(|date| self.opens_on(date.weekday()))
+            // A start skipped by a DST change does not open the window that day
+            .filter_map(|date| {
+                tz.from_local_datetime(&date.and_time(self.start))
+                    .earliest()
+            })
+            .map(|opening| opening.naive_utc())
+            .find(|opening| *opening > now)
+    }
+}
+
+/// Date (UTC) until which a notification of the severity must be held, None if it can be sent now
+pub fn held_until(windows: &[DeliveryWindow], severity: Severity) -> Option<NaiveDateTime> {
+    let now = Utc::now().naive_utc();
+    let windows: Vec<&DeliveryWindow> = windows.iter().filter(|w| w.applies_to(severity)).collect();
+    if windows.is_empty() || windows.iter().any(|w| w.is_open(now)) {
+        return None;
+    }
+
+    windows.iter().filter_map(|w| w.next_opening(now)).min()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDateTime, NaiveTime, Weekday};
+
+    use super::DeliveryWindow;
+
+    fn window(days: Option<Vec<Weekday>>, start: &str, end: &str) -> DeliveryWindow {
+        DeliveryWindow {
+            severity: None,
+            days,
+            start: NaiveTime::parse_from_str(start, "%H:%M").unwrap(),
+            end: NaiveTime::parse_from_str(end, "%H:%M").unwrap(),
+            timezone: Some(chrono_tz::Europe::Paris),
+        }
+    }
+
+    fn utc(date: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn over_midnight() {
+        // Opens on friday at 22:00 (Paris, UTC+2 in october) and closes on saturday at 06:00
+        let window = window(Some(vec![Weekday::Fri]), "22:00", "06:00");
+
+        assert!(!window.is_open(utc("2026-10-16 19:00")));
+        assert!(window.is_open(utc("2026-10-16 20:00")));
+        assert!(window.is_open(utc("2026-10-17 01:00")));
+        assert!(!window.is_open(utc("2026-10-17 04:00")));
+        // Saturday evening is not part of the window of friday
+        assert!(!window.is_open(utc("2026-10-17 20:30")));
+    }
+
+    #[test]
+    fn start_equals_end() {
+        let always = window(None, "08:00", "08:00");
+        assert!(always.is_open(utc("2026-10-17 05:59")));
+        assert!(always.is_open(utc("2026-10-17 06:00")));
+
+        // The 24 hours start on monday at 08:00 (Paris), so the monday morning is closed
+        let monday = window(Some(vec![Weekday::Mon]), "08:00", "08:00");
+        assert!(!monday.is_open(utc("2026-10-19 05:00")));
+        assert!(monday.is_open(utc("2026-10-19 06:00")));
+        assert!(monday.is_open(utc("2026-10-20 05:59")));
+        assert!(!monday.is_open(utc("2026-10-20 06:00")));
+    }
+
+    #[test]
+    fn next_opening_after_the_weekend() {
+        let office = window(
+            Some(vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ]),
+            "09:00",
+            "17:00",
+        );
+
+        // Friday evening and saturday wait for monday at 09:00 in Paris
+        assert!(!office.is_open(utc("2026-10-16 16:00")));
+        assert_eq!(
+            office.next_opening(utc("2026-10-16 16:00")),
+            Some(utc("2026-10-19 07:00"))
+        );
+        assert_eq!(
+            office.next_opening(utc("2026-10-17 10:00")),
+            Some(utc("2026-10-19 07:00"))
+        );
+        // Before the opening of the day
+        assert_eq!(
+            office.next_opening(utc("2026-10-19 06:00")),
+            Some(utc("2026-10-19 07:00"))
+        );
+    }
+
+    #[test]
+    fn dst_gap() {
+        // 02:30 does not exist in Paris on 2026-03-29 (02:00 -> 03:00), so the window opens the next day
+        let window = window(None, "02:30", "04:00");
+        assert_eq!(
+            window.next_opening(utc("2026-03-28 23:00")),
+            Some(utc("2026-03-30 00:30"))
+        );
+        // The end is after the gap, so that the window is still open once in CEST
+        assert!(window.is_open(utc("2026-03-29 01:30")));
+        assert!(!window.is_open(utc("2026-03-29 02:00")));
+    }
+
+    #[test]
+    fn never_opens() {
+        let window = window(Some(Vec::new()), "09:00", "17:00");
+        assert!(!window.is_open(utc("2026-10-19 10:00")));
+        assert_eq!(window.next_opening(utc("2026-10-19 10:00")), None);
+    }
+}
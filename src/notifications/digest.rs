@@ -0,0 +1,374 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use chrono::prelude::Utc;
+use lettre::message::Mailbox;
+use once_cell::sync::Lazy;
+use sailfish::TemplateOnce;
+use serde::Deserialize;
+use simd_json::{json, prelude::ValueAsMutObject, OwnedValue};
+use sproot::Pool;
+
+use super::{
+    i18n::{fill, Catalog, Lang},
+    mail::{self, MailContent, MailThread},
+    outbox,
+    templates::{self, TemplateError},
+    Event, EventKind, NotifierError,
+};
+use crate::{monitoring::Severity, utils::spawn_periodic, CONFIG};
+
+/// Interval at which the worker looks for the digests to send
+const WORKER_INTERVAL: Duration = Duration::from_secs(5);
+
+// Lazy static of the digests being aggregated, by (receiver, group)
+static DIGESTS: Lazy<Mutex<HashMap<(String, String), Digest>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Enum representing how the incidents are grouped in a digest
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestGroup {
+    #[default]
+    Host,
+    Cid,
+}
+
+/// Structure representing a line of the digest template
+struct DigestLine {
+    title: &'static str,
+    alert_name: String,
+    hostname: String,
+    severity: String,
+    result: String,
+    date: String,
+}
+
+/// Structure representing the digest template html sent by mail
+#[derive(TemplateOnce)]
+#[template(path = "digest.stpl")]
+struct DigestTemplate<'a> {
+    t: &'a Catalog,
+    color: &'a str,
+    title: &'a str,
+    group: &'a str,
+    items: &'a [DigestLine],
+    sent_at: &'a str,
+}
+
+/// Structure representing the digest template text sent by mail
+#[derive(TemplateOnce)]
+#[template(path = "digest.txt.stpl")]
+struct DigestTextTemplate<'a> {
+    t: &'a Catalog,
+    title: &'a str,
+    group: &'a str,
+    items: &'a [DigestLine],
+    sent_at: &'a str,
+}
+
+/// Structure holding the mails aggregated for a receiver and a group
+struct Digest {
+    opened_at: Instant,
+    sender: Mailbox,
+    receiver: Mailbox,
+    lang: Lang,
+    group: String,
+    critical: bool,
+    lines: Vec<DigestLine>,
+    // The original (subject, content, thread) of the mails, sent as is if alone
+    mails: Vec<(String, MailContent, MailThread)>,
+    // The outbox entries of the mails, completed once the digest is sent
+    entries: Vec<i64>,
+}
+
+/// Add the mail of the Event to the digest of each of the receivers, all sharing the same Lang.
+pub fn push(
+    event: &Event,
+    outbox_id: i64,
+    lang: Lang,
+    receivers: &[Mailbox],
+    subject: String,
+    content: MailContent,
+    thread: MailThread,
+) {
+    let (group_key, group) = match CONFIG.digest_group_by {
+        DigestGroup::Host => (
+            event.incident.host_uuid.to_owned(),
+            event.incident.hostname.to_owned(),
+        ),
+        DigestGroup::Cid => (
+            event.incident.cid.to_string(),
+            event.incident.cid.to_string(),
+        ),
+    };
+    let severity = Severity::from(event.incident.severity);
+    let critical = matches!(
+        (event.kind, severity),
+        (
+            EventKind::New | EventKind::Escalated | EventKind::Reminder,
+            Severity::Critical
+        )
+    );
+    let catalog = lang.catalog();
+
+    let mut digests = DIGESTS.lock().unwrap();
+    for receiver in receivers {
+        let digest = digests
+            .entry((receiver.to_string(), group_key.to_owned()))
+            .or_insert_with(|| Digest {
+                opened_at: Instant::now(),
+                sender: event
+                    .destination
+                    .sender
+                    .clone()
+                    .unwrap_or_else(|| CONFIG.smtp_email_sender.clone()),
+                receiver: receiver.clone(),
+                lang,
+                group: group.to_owned(),
+                critical: false,
+                lines: Vec::new(),
+                mails: Vec::new(),
+                entries: Vec::new(),
+            });
+
+        digest.critical |= critical;
+        digest.lines.push(DigestLine {
+            title: catalog.title(event.kind),
+            alert_name: event.alert.name.to_owned(),
+            hostname: event.incident.hostname.to_owned(),
+            severity: catalog.severity_name(severity).to_owned(),
+            result: event.incident.result.to_owned(),
+            date: lang.format_date(event.incident.updated_at),
+        });
+        digest
+            .mails
+            .push((subject.to_owned(), content.clone(), thread));
+        digest.entries.push(outbox_id);
+    }
+}
+
+/// Send the digest, or the original mail if it's the only one.
+fn send_digest(digest: &Digest) -> Result<(), NotifierError> {
+    let receivers = [digest.receiver.clone()];
+
+    if let [(subject, content, thread)] = digest.mails.as_slice() {
+        return mail::send_mail(
+            &digest.sender,
+            &receivers,
+            subject.to_owned(),
+            content.clone(),
+            Some(*thread),
+        );
+    }
+
+    let lang = &digest.lang;
+    // A failing custom template should not prevent the digest from being sent
+    let content = render_digest(lang, digest.critical, &digest.group, &digest.lines)
+        .unwrap_or_else(|err| {
+            error!("Templates: {}, using the built-in ones", err);
+            let (html, text) = render_builtin(lang, digest.critical, &digest.group, &digest.lines);
+            MailContent {
+                html: Some(html),
+                text,
+                sparkline: None,
+            }
+        });
+
+    mail::send_mail(
+        &digest.sender,
+        &receivers,
+        fill(
+            lang.catalog().digest_subject,
+            &[
+                ("group", &digest.group),
+                ("count", &digest.lines.len().to_string()),
+                ("date", &lang.format_small_date(Utc::now().naive_utc())),
+            ],
+        ),
+        content,
+        None,
+    )
+}
+
+/// Render the built-in (html, text) templates of the digest.
+fn render_builtin(
+    lang: &Lang,
+    critical: bool,
+    group: &str,
+    lines: &[DigestLine],
+) -> (String, String) {
+    let t = lang.catalog();
+    let title = digest_title(lang, lines.len());
+    let sent_at = lang.format_date(Utc::now().naive_utc());
+    // SAFETY: render_once() can never fails except if called from the template itself.
+    let html = DigestTemplate {
+        t,
+        color: digest_color(critical),
+        title: &title,
+        group,
+        items: lines,
+        sent_at: &sent_at,
+    }
+    .render_once()
+    .unwrap();
+    let text = DigestTextTemplate {
+        t,
+        title: &title,
+        group,
+        items: lines,
+        sent_at: &sent_at,
+    }
+    .render_once()
+    .unwrap();
+
+    (html, text)
+}
+
+/// Render the digest mail (html and text).
+///
+/// The templates loaded from the templates_dir are used if any, the built-in ones otherwise.
+fn render_digest(
+    lang: &Lang,
+    critical: bool,
+    group: &str,
+    lines: &[DigestLine],
+) -> Result<MailContent, TemplateError> {
+    let items: Vec<OwnedValue> = lines
+        .iter()
+        .map(|line| {
+            json!({
+                "title": line.title,
+                "alert_name": line.alert_name.to_owned(),
+                "hostname": line.hostname.to_owned(),
+                "severity": line.severity.to_owned(),
+                "result": line.result.to_owned(),
+                "date": line.date.to_owned(),
+            })
+        })
+        .collect();
+    let mut ctx = json!({
+        "color": digest_color(critical),
+        "title": digest_title(lang, lines.len()),
+        "count": lines.len(),
+        "group": group,
+        "items": items,
+        "sent_at": lang.format_date(Utc::now().naive_utc()),
+    });
+    if let Some(obj) = ctx.as_object_mut() {
+        // SAFETY: the Catalog only contains strings, it can always be converted
+        obj.insert(
+            "t".into(),
+            simd_json::serde::to_owned_value(lang.catalog()).unwrap(),
+        );
+    }
+
+    let html = templates::render("digest.html", &ctx).transpose()?;
+    let text = templates::render("digest.txt", &ctx).transpose()?;
+
+    let (html, text) = match (html, text) {
+        (Some(html), Some(text)) => (html, text),
+        (html, text) => {
+            let (builtin_html, builtin_text) = render_builtin(lang, critical, group, lines);
+            (html.unwrap_or(builtin_html), text.unwrap_or(builtin_text))
+        }
+    };
+
+    Ok(MailContent {
+        html: Some(html),
+        text,
+        sparkline: None,
+    })
+}
+
+/// Render the digest mail against sample data.
+pub fn render_sample() -> Result<MailContent, TemplateError> {
+    let lang = Lang::default();
+    let catalog = lang.catalog();
+    let date = lang.format_date(Utc::now().naive_utc());
+    let lines = [
+        DigestLine {
+            title: catalog.title(EventKind::New),
+            alert_name: "disk_usage".to_owned(),
+            hostname: "server-01".to_owned(),
+            severity: catalog.severity_name(Severity::Warning).to_owned(),
+            result: "84.12".to_owned(),
+            date: date.to_owned(),
+        },
+        DigestLine {
+            title: catalog.title(EventKind::Escalated),
+            alert_name: "disk_usage".to_owned(),
+            hostname: "server-01".to_owned(),
+            severity: catalog.severity_name(Severity::Critical).to_owned(),
+            result: "92.41".to_owned(),
+            date,
+        },
+    ];
+
+    render_digest(&lang, true, "server-01", &lines)
+}
+
+fn digest_title(lang: &Lang, count: usize) -> String {
+    fill(
+        lang.catalog().digest_title,
+        &[("count", &count.to_string())],
+    )
+}
+
+fn digest_color(critical: bool) -> &'static str {
+    if critical {
+        "#d96f6f"
+    } else {
+        "#e7d756"
+    }
+}
+
+/// Send the digests whose aggregation window is over.
+///
+/// The outbox entries of a digest which could not be sent are retried (and
+/// aggregated again) by the outbox, up to their dead-letter.
+fn flush(window: Duration, pool: &Pool) {
+    // Keep the digests until their entries can be completed
+    let mut conn = match pool.get() {
+        Ok(conn) => conn,
+        Err(err) => {
+            error!("Digest: cannot get a connection: {}", err);
+            return;
+        }
+    };
+
+    let ready: Vec<Digest> = {
+        let mut digests = DIGESTS.lock().unwrap();
+        let keys: Vec<(String, String)> = digests
+            .iter()
+            .filter(|(_, d)| d.opened_at.elapsed() >= window)
+            .map(|(k, _)| k.clone())
+            .collect();
+        keys.into_iter()
+            .filter_map(|k| digests.remove(&k))
+            .collect()
+    };
+
+    for digest in ready {
+        let res = send_digest(&digest);
+        match &res {
+            Ok(_) => info!(
+                "Digest for {} sent successfully to {}!",
+                digest.group, digest.receiver
+            ),
+            Err(err) => error!(
+                "Could not send the digest for {} to {}: {}",
+                digest.group, digest.receiver, err
+            ),
+        }
+        outbox::complete_digest(&mut conn, &digest.entries, &res);
+    }
+}
+
+/// Start the worker sending the digests, supervised by Bastion.
+pub fn start_worker(window: Duration, pool: Pool) {
+    spawn_periodic("Digest", WORKER_INTERVAL, move || flush(window, &pool));
+}
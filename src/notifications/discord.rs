@@ -0,0 +1,131 @@
+use simd_json::{json, OwnedValue};
+use sproot::ConnType;
+
+use super::{Event, Notifier, NotifierError, AGENT};
+use crate::utils::config::Config;
+
+/// Notifier sending the incidents as embeds to a Discord webhook
+pub struct DiscordNotifier {
+    url: String,
+}
+
+impl DiscordNotifier {
+    /// Build the DiscordNotifier if a Discord webhook is configured.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        config.discord_webhook_url.as_ref().map(|url| Self {
+            url: url.to_owned(),
+        })
+    }
+
+    fn build_message(event: &Event) -> OwnedValue {
+        let fields: Vec<OwnedValue> = event
+            .fields()
+            .into_iter()
+            .map(|(label, value)| {
+                json!({
+                    "name": label,
+                    "value": format!("`{}`", value),
+                    "inline": true,
+                })
+            })
+            .collect();
+
+        json!({
+            "username": "Speculare",
+            "embeds": [{
+                "title": format!("{} - {}", event.title(), event.incident.hostname),
+                "description": event.summary(),
+                // Discord expects the color as an integer
+                "color": u32::from_str_radix(event.color().trim_start_matches('#'), 16).unwrap_or_default(),
+                "fields": fields,
+            }],
+        })
+    }
+}
+
+impl Notifier for DiscordNotifier {
+    fn name(&self) -> &'static str {
+        "Discord"
+    }
+
+    fn notify(&self, _conn: &mut ConnType, event: &Event) -> Result<(), NotifierError> {
+        self.notify_event(event)
+    }
+
+    fn notify_summary(&self, text: &str) -> Result<(), NotifierError> {
+        let payload = simd_json::to_string(&json!({ "content": text }))
+            .map_err(|err| NotifierError(format!("could not serialize the payload: {}", err)))?;
+
+        self.post(&payload)
+    }
+}
+
+impl DiscordNotifier {
+    /// Post the Event, the database not being needed for Discord
+    fn notify_event(&self, event: &Event) -> Result<(), NotifierError> {
+        let payload = simd_json::to_string(&Self::build_message(event))
+            .map_err(|err| NotifierError(format!("could not serialize the payload: {}", err)))?;
+
+        self.post(&payload)
+    }
+
+    fn post(&self, payload: &str) -> Result<(), NotifierError> {
+        AGENT
+            .post(&self.url)
+            .set("Content-Type", "application/json")
+            .send_string(payload)
+            .map(|_| ())
+            .map_err(|err| NotifierError(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use simd_json::{json, OwnedValue};
+
+    use super::DiscordNotifier;
+    use crate::notifications::{mock, routing::Destination, Event, EventKind};
+
+    #[test]
+    fn notify() {
+        let (url, received) = mock::serve_once("");
+        let notifier = DiscordNotifier {
+            url: format!("{}/api/webhooks/1/token", url),
+        };
+        let (alert, incident) = (mock::alert(), mock::incident(1));
+        let event = Event {
+            kind: EventKind::New,
+            alert: &alert,
+            incident: &incident,
+            destination: &Destination::default(),
+            outbox_id: Some(42),
+        };
+        notifier.notify_event(&event).unwrap();
+
+        let request = received.recv().unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.url, "/api/webhooks/1/token");
+        // The token is part of the url of the webhook
+        assert_eq!(request.authorization, None);
+        assert_eq!(request.content_type.as_deref(), Some("application/json"));
+        let body: OwnedValue = simd_json::from_slice(&mut request.body.into_bytes()).unwrap();
+        let fields: Vec<OwnedValue> = event
+            .fields()
+            .into_iter()
+            .map(|(label, value)| json!({"name": label, "value": format!("`{}`", value), "inline": true}))
+            .collect();
+        assert_eq!(
+            body,
+            json!({
+                "username": "Speculare",
+                "embeds": [{
+                    "title": format!("{} - web-1", event.title()),
+                    "description": event.summary(),
+                    // #d96f6f, the color of the critical incidents
+                    "color": 14249839,
+                    "fields": fields,
+                }],
+            })
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use std::fs;
+
+use lettre::{
+    message::dkim::{DkimConfig, DkimSigningAlgorithm, DkimSigningKey},
+    Message,
+};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+use crate::CONFIG;
+
+// Lazy static of the DKIM configs used to sign the mails by domain, empty if the signing is disabled
+static DKIM: Lazy<Vec<(String, DkimConfig)>> = Lazy::new(|| match get_dkim_configs() {
+    Ok(configs) => configs,
+    Err(e) => {
+        error!("DKIM: cannot build the signing config: {}", e);
+        std::process::exit(1);
+    }
+});
+
+/// Enum representing the algorithm of the DKIM private key
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DkimAlgorithm {
+    #[default]
+    Rsa,
+    Ed25519,
+}
+
+/// Structure representing the DKIM key of another sending domain, as defined in the Config.
+///
+/// Used to sign the mails of the tenants whose sender is on their own domain.
+#[derive(Debug, Deserialize, Clone)]
+pub struct DkimKey {
+    pub domain: String,
+    pub selector: String,
+    pub private_key_path: String,
+    #[serde(default)]
+    pub algorithm: DkimAlgorithm,
+}
+
+/// Load the signing keys at startup instead of at the first mail, and warn about
+/// the senders whose mails won't be signed.
+pub fn init() {
+    if DKIM.is_empty() {
+        return;
+    }
+    for (domain, _) in DKIM.iter() {
+        info!("DKIM: signing the mails of {}", domain);
+    }
+
+    let senders = std::iter::once(&CONFIG.smtp_email_sender)
+        .chain(CONFIG.tenants.iter().filter_map(|t| t.sender.as_ref()));
+    for sender in senders {
+        let domain = sender.email.domain();
+        if config_for(domain).is_none() {
+            warn!(
+                "DKIM: no key for {}, the mails from {} won't be signed and may fail DMARC",
+                domain, sender
+            );
+        }
+    }
+}
+
+/// Sign the email with the DKIM key of the domain of its sender, if any.
+///
+/// A mail signed for another domain than the one of its From fails DMARC
+/// alignment anyway, so that it is better left unsigned.
+pub fn sign(email: &mut Message, sender_domain: &str) {
+    if let Some(config) = config_for(sender_domain) {
+        email.sign(config);
+    }
+}
+
+/// Config of the domain, or of the closest parent one (relaxed alignment)
+fn config_for(domain: &str) -> Option<&'static DkimConfig> {
+    let domain = domain.to_ascii_lowercase();
+    DKIM.iter()
+        .filter(|(key_domain, _)| {
+            domain == *key_domain || domain.ends_with(&format!(".{}", key_domain))
+        })
+        .max_by_key(|(key_domain, _)| key_domain.len())
+        .map(|(_, config)| config)
+}
+
+fn get_dkim_configs() -> Result<Vec<(String, DkimConfig)>, String> {
+    let mut keys = Vec::new();
+    match (&CONFIG.dkim_selector, &CONFIG.dkim_private_key_path) {
+        (Some(selector), Some(path)) => keys.push(DkimKey {
+            // The domain must match the one of the From header for DMARC to pass
+            domain: CONFIG
+                .dkim_domain
+                .to_owned()
+                .unwrap_or_else(|| CONFIG.smtp_email_sender.email.domain().to_owned()),
+            selector: selector.to_owned(),
+            private_key_path: path.to_owned(),
+            algorithm: CONFIG.dkim_algorithm,
+        }),
+        (None, None) => {}
+        _ => return Err("dkim_selector and dkim_private_key_path must be set together".to_owned()),
+    };
+    keys.extend(CONFIG.dkim_keys.iter().cloned());
+
+    keys.into_iter()
+        .map(|key| {
+            let path = &key.private_key_path;
+            let pem =
+                fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+            let algorithm = match key.algorithm {
+                DkimAlgorithm::Rsa => DkimSigningAlgorithm::Rsa,
+                DkimAlgorithm::Ed25519 => DkimSigningAlgorithm::Ed25519,
+            };
+            let signing_key = DkimSigningKey::new(pem.trim(), algorithm)
+                .map_err(|e| format!("invalid key {}: {}", path, e))?;
+
+            let domain = key.domain.to_ascii_lowercase();
+            Ok((
+                domain.clone(),
+                DkimConfig::default_config(key.selector, domain, signing_key),
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,294 @@
+use std::time::Duration;
+
+use chrono::{prelude::Utc, NaiveDateTime};
+use diesel::prelude::*;
+use lettre::message::Mailbox;
+use serde::Deserialize;
+use sproot::{
+    apierrors::ApiError,
+    models::{Alerts, BaseCrud, Incidents},
+    ConnType, Pool,
+};
+
+use super::{acks, oncall, routing, routing::Destination, send, Event, EventKind};
+use crate::{
+    monitoring::{silences, IncidentStatus},
+    schema::incidents_escalations,
+    utils::{
+        config::{mailboxes_deser, Config},
+        spawn_periodic,
+    },
+    CONFIG,
+};
+
+/// Interval at which the worker looks for the incidents to escalate
+const WORKER_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Structure representing a time based escalation policy, as defined in the Config.
+///
+/// While an incident is active and not acknowledged, its tiers are notified one
+/// after the other, then the last one is notified again every `repeat` minutes.
+#[derive(Debug, Deserialize, Clone)]
+pub struct EscalationPolicy {
+    pub name: String,
+    pub tiers: Vec<EscalationTier>,
+    /// Minutes between two reminders once every tier was notified, never if not set
+    pub repeat: Option<u64>,
+}
+
+/// Structure representing a tier of an escalation policy
+#[derive(Debug, Deserialize, Clone)]
+pub struct EscalationTier {
+    /// Minutes since the start of the incident before notifying this tier
+    pub after: u64,
+    /// Mailboxes receiving the mails for this tier
+    #[serde(default, deserialize_with = "mailboxes_deser")]
+    pub receivers: Vec<Mailbox>,
+    /// Name of the on-call schedule whose current member also receives the mails
+    pub oncall: Option<String>,
+    /// Channels (smtp, slack, ...) used for this tier, the ones of the incident's Destination if not set
+    pub channels: Option<Vec<String>>,
+}
+
+/// Structure representing the escalation of an active incident
+#[derive(Queryable, Selectable, Insertable, Debug)]
+#[diesel(table_name = incidents_escalations)]
+struct Escalation {
+    incident_id: i32,
+    alerts_id: i64,
+    /// Number of tiers already notified
+    tier: i32,
+    next_at: NaiveDateTime,
+}
+
+/// Check that the policies used by the Routes exist and have tiers, and that the
+/// tiers of the policies used by a tenant only use the channels of its profile.
+pub fn check_policies(config: &Config) -> Result<(), String> {
+    if let Some(policy) = config
+        .escalation_policies
+        .iter()
+        .find(|p| p.tiers.is_empty())
+    {
+        return Err(format!("policy {} has no tiers", policy.name));
+    }
+
+    for route in &config.routes {
+        let name = match &route.escalation_policy {
+            Some(name) => name,
+            None => continue,
+        };
+        let policy = match find_policy(config, name) {
+            Some(policy) => policy,
+            None => return Err(format!("unknown policy {}", name)),
+        };
+
+        // The other channels are shared by every tenant, the reminders must not leak there
+        let profile = match config
+            .tenants
+            .iter()
+            .find(|t| route.cid.as_ref() == Some(&t.cid))
+        {
+            Some(profile) => profile,
+            None => continue,
+        };
+        for channel in policy
+            .tiers
+            .iter()
+            .filter_map(|tier| tier.channels.as_ref())
+            .flatten()
+        {
+            if !profile
+                .channels
+                .iter()
+                .any(|c| c.eq_ignore_ascii_case(channel))
+            {
+                return Err(format!(
+                    "policy {} uses the channel {} which is not one of the tenant {}",
+                    name, channel, profile.cid
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn find_policy<'a>(config: &'a Config, name: &str) -> Option<&'a EscalationPolicy> {
+    config.escalation_policies.iter().find(|p| p.name == name)
+}
+
+/// Start escalating the new incident if its Destination has a policy
+pub fn schedule(conn: &mut ConnType, incident: &Incidents, destination: &Destination) {
+    let policy = match destination
+        .escalation_policy
+        .as_ref()
+        .and_then(|name| find_policy(&CONFIG, name))
+    {
+        Some(policy) => policy,
+        None => return,
+    };
+
+    let res = diesel::insert_into(incidents_escalations::table)
+        .values(&Escalation {
+            incident_id: incident.id,
+            alerts_id: incident.alerts_id,
+            tier: 0,
+            next_at: incident.started_at + minutes(policy.tiers[0].after),
+        })
+        .on_conflict_do_nothing()
+        .execute(conn);
+    if let Err(err) = res {
+        error!(
+            "Escalation: could not schedule the incident {}: {}",
+            incident.id, err
+        );
+    }
+}
+
+/// Stop escalating the incident
+pub fn cancel(conn: &mut ConnType, incident_id: i32) {
+    if let Err(err) = diesel::delete(incidents_escalations::table.find(incident_id)).execute(conn) {
+        error!(
+            "Escalation: could not cancel the incident {}: {}",
+            incident_id, err
+        );
+    }
+}
+
+/// Destination of the tier, keeping the sender, quiet hours and delivery windows of the incident's one,
+/// and its channels if the tier has none (so that a tenant's reminders stay on its own channels).
+fn tier_destination(base: &Destination, tier: &EscalationTier) -> Destination {
+    Destination {
+        receivers: oncall::with_on_call(&tier.receivers, tier.oncall.as_ref()),
+        channels: tier.channels.clone().or_else(|| base.channels.clone()),
+        sender: base.sender.clone(),
+        quiet_hours: base.quiet_hours.clone(),
+        escalation_policy: None,
+        delivery_windows: base.delivery_windows.clone(),
+    }
+}
+
+/// Destination of the last tier notified for the incident, used to retry its reminders
+pub fn current_destination(
+    conn: &mut ConnType,
+    alert: &Alerts,
+    incident: &Incidents,
+) -> Option<Destination> {
+    let escalation: Escalation = incidents_escalations::table
+        .find(incident.id)
+        .select(Escalation::as_select())
+        .first(conn)
+        .ok()?;
+    let base = routing::resolve(&CONFIG, alert, incident);
+    let policy = find_policy(&CONFIG, base.escalation_policy.as_ref()?)?;
+
+    let idx = (escalation.tier.max(1) as usize - 1).min(policy.tiers.len() - 1);
+    Some(tier_destination(&base, &policy.tiers[idx]))
+}
+
+/// Notify the next tier of the incidents whose escalation is due
+fn escalate_due(conn: &mut ConnType) -> Result<(), ApiError> {
+    let escalations: Vec<Escalation> = incidents_escalations::table
+        .filter(incidents_escalations::next_at.le(Utc::now().naive_utc()))
+        .order(incidents_escalations::next_at)
+        .select(Escalation::as_select())
+        .load(conn)?;
+
+    for escalation in escalations {
+        let incident = match Incidents::get_specific(conn, escalation.incident_id) {
+            Ok(incident) => incident,
+            Err(_) => {
+                cancel(conn, escalation.incident_id);
+                continue;
+            }
+        };
+        // Someone is on it or it's over, the next tiers don't need to be bothered
+        if acks::status(conn, &incident) != IncidentStatus::Active {
+            cancel(conn, incident.id);
+            continue;
+        }
+        let alert = match Alerts::get_specific(conn, escalation.alerts_id) {
+            Ok(alert) => alert,
+            Err(_) => {
+                cancel(conn, incident.id);
+                continue;
+            }
+        };
+        // Wait for the end of the maintenance, the incident may resolve on its own meanwhile
+        if silences::find(conn, &alert).is_some() {
+            continue;
+        }
+
+        let base = routing::resolve(&CONFIG, &alert, &incident);
+        // The policy may have been removed from the Config since the incident started
+        let policy = match base
+            .escalation_policy
+            .as_ref()
+            .and_then(|name| find_policy(&CONFIG, name))
+        {
+            Some(policy) => policy,
+            None => {
+                cancel(conn, incident.id);
+                continue;
+            }
+        };
+
+        let notified = escalation.tier as usize;
+        let idx = notified.min(policy.tiers.len() - 1);
+        let destination = tier_destination(&base, &policy.tiers[idx]);
+        // Wait for the end of the quiet hours (or the next window) instead of skipping the tier
+        if destination.held_until(&incident).is_some() {
+            continue;
+        }
+
+        info!(
+            "Escalation: incident {} still active, notifying the tier {} of policy {}",
+            incident.id,
+            idx + 1,
+            policy.name
+        );
+        send(
+            conn,
+            &Event {
+                kind: EventKind::Reminder,
+                alert: &alert,
+                incident: &incident,
+                destination: &destination,
+                outbox_id: None,
+            },
+        );
+
+        let next_at = match (policy.tiers.get(notified + 1), policy.repeat) {
+            (Some(next), _) => incident.started_at + minutes(next.after),
+            (None, Some(repeat)) => Utc::now().naive_utc() + minutes(repeat),
+            (None, None) => {
+                cancel(conn, incident.id);
+                continue;
+            }
+        };
+        diesel::update(incidents_escalations::table.find(incident.id))
+            .set((
+                incidents_escalations::tier.eq(escalation.tier + 1),
+                incidents_escalations::next_at.eq(next_at),
+            ))
+            .execute(conn)?;
+    }
+
+    Ok(())
+}
+
+fn minutes(minutes: u64) -> chrono::Duration {
+    chrono::Duration::minutes(minutes as i64)
+}
+
+/// Start the worker escalating the incidents, supervised by Bastion.
+pub fn start_worker(pool: Pool) {
+    spawn_periodic("Escalation", WORKER_INTERVAL, move || {
+        let res = pool
+            .get()
+            .map_err(ApiError::from)
+            .and_then(|mut conn| escalate_due(&mut conn));
+        if let Err(err) = res {
+            error!("Escalation: cannot escalate the incidents: {}", err);
+        }
+    });
+}
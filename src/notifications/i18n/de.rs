@@ -0,0 +1,54 @@
+use super::Catalog;
+
+pub const CATALOG: Catalog = Catalog {
+    date_format: "%d.%m.%Y %H:%M:%S %Z",
+    date_small_format: "%d.%m.%Y um %H:%M %Z",
+
+    new_title: "Neuer Vorfall",
+    escalated_title: "Vorfall eskaliert",
+    resolved_title: "Vorfall behoben",
+    reminder_title: "Vorfall weiterhin aktiv",
+    new_summary: "Auf {hostname} wurde soeben ein neuer Vorfall ausgelöst. Auslöser war {alert}, der aktuelle Schweregrad ist: {severity}.",
+    escalated_summary: "Der Vorfall auf {hostname} wurde auf einen höheren Schweregrad gestuft: {severity}.",
+    resolved_summary: "Der von {alert} ausgelöste Vorfall auf {hostname} wurde behoben.",
+    reminder_summary: "Der von {alert} ausgelöste Vorfall auf {hostname} ist weiterhin aktiv ({severity}) und wurde von niemandem bestätigt.",
+
+    greeting: "Hallo!",
+    new_intro: "Soeben wurde ein neuer Vorfall ausgelöst. Auslöser war {alert}, der aktuelle Schweregrad ist: {severity}.",
+    escalated_intro: "Der Vorfall wurde auf einen höheren Schweregrad gestuft: {severity}.",
+    escalated_advice: "Sie sollten umgehend Maßnahmen ergreifen, um das Problem zu beheben.",
+    resolved_intro: "Der von {alert} ausgelöste Vorfall wurde behoben.",
+    reminder_intro: "Der von {alert} ausgelöste Vorfall ist weiterhin aktiv ({severity}) und wurde noch von niemandem bestätigt.",
+    details: "Unten finden Sie die Details des Vorfalls, darunter die Einstellungen und Schwellenwerte des Alarms.",
+    see_details: "Details ansehen",
+    having_trouble: "Probleme?",
+
+    lookup: "Abfrage",
+    result: "Ergebnis",
+    warning: "Warnung",
+    critical: "Kritisch",
+    severity: "Schweregrad",
+    started_at: "Begonnen am",
+    latest_update: "Letzte Aktualisierung",
+    resolved_at: "Behoben am",
+    at: "Am",
+    on: "auf",
+    trend: "Verlauf der letzten Ergebnisse",
+
+    digest_title: "{count} gemeldete Vorfälle",
+    digest_intro: "In kurzer Zeit wurden mehrere Vorfälle gemeldet, sie werden in dieser Zusammenfassung gebündelt, statt einzeln versendet zu werden.",
+    digest_details: "Unten finden Sie die Details jedes Vorfalls, in der Reihenfolge ihrer Meldung.",
+    digest_sent_at: "Zusammenfassung gesendet am",
+    digest_subject: "{group} [{count} Vorfälle] - {date}",
+
+    suppressed_subject: "Speculare: {count} Benachrichtigungen unterdrückt",
+    suppressed_text: "{count} Benachrichtigungen wurden unterdrückt, da das Sendelimit erreicht wurde. Details finden Sie in Ihrem Dashboard.",
+
+    acknowledge: "Bestätigen",
+    ack_title: "Vorfall bestätigen",
+    ack_confirm: "Eskalationen des Vorfalls {alert} auf {hostname} werden nicht mehr gemeldet, er wird bis zu seiner Behebung weiter überwacht.",
+    ack_by: "Ihr Name oder Ihre E-Mail",
+    ack_done: "Der Vorfall {alert} auf {hostname} wurde von {by} am {date} bestätigt.",
+    ack_resolved: "Der Vorfall {alert} auf {hostname} ist bereits behoben.",
+    ack_invalid: "Dieser Link ist ungültig oder abgelaufen.",
+};
@@ -0,0 +1,54 @@
+use super::Catalog;
+
+pub const CATALOG: Catalog = Catalog {
+    date_format: "%Y-%m-%d %H:%M:%S %Z",
+    date_small_format: "%d %b %Y at %H:%M %Z",
+
+    new_title: "New incident started",
+    escalated_title: "Incident escalated",
+    resolved_title: "Incident resolved",
+    reminder_title: "Incident still active",
+    new_summary: "A new incident just started on {hostname}. It was triggered by {alert} and its current severity is: {severity}.",
+    escalated_summary: "The incident on {hostname} has been updated to a higher level of severity: {severity}.",
+    resolved_summary: "The incident on {hostname} triggered by {alert} has been resolved.",
+    reminder_summary: "The incident on {hostname} triggered by {alert} is still active ({severity}) and nobody acknowledged it.",
+
+    greeting: "Hey !",
+    new_intro: "A new incident just started. It was triggered by {alert} and its current severity is: {severity}.",
+    escalated_intro: "The incident has been updated to a higher level of severity: {severity}.",
+    escalated_advice: "You may want to take actions immediately to resolve this issue.",
+    resolved_intro: "The incident triggered by {alert} has been resolved.",
+    reminder_intro: "The incident triggered by {alert} is still active ({severity}) and nobody acknowledged it yet.",
+    details: "You can see the details of the incident below. Those details include the settings, threshold, etc of the alert.",
+    see_details: "see details",
+    having_trouble: "Having trouble?",
+
+    lookup: "Lookup",
+    result: "Result",
+    warning: "Warning",
+    critical: "Critical",
+    severity: "Severity",
+    started_at: "Started at",
+    latest_update: "Latest update",
+    resolved_at: "Resolved at",
+    at: "At",
+    on: "on",
+    trend: "Trend of the last results",
+
+    digest_title: "{count} incidents reported",
+    digest_intro: "Several incidents were reported in a short period of time, they are grouped in this digest instead of being sent one by one.",
+    digest_details: "You can see the details of each incident below, in the order they were reported.",
+    digest_sent_at: "Digest sent at",
+    digest_subject: "{group} [{count} incidents] - {date}",
+
+    suppressed_subject: "Speculare: {count} notifications suppressed",
+    suppressed_text: "{count} notifications were suppressed because the rate limit was reached, check your dashboard for the details.",
+
+    acknowledge: "Acknowledge",
+    ack_title: "Acknowledge the incident",
+    ack_confirm: "The escalations of the incident {alert} on {hostname} won't be notified anymore, it keeps being monitored until it's resolved.",
+    ack_by: "Your name or email",
+    ack_done: "The incident {alert} on {hostname} was acknowledged by {by} on {date}.",
+    ack_resolved: "The incident {alert} on {hostname} is already resolved.",
+    ack_invalid: "This link is invalid or has expired.",
+};
@@ -0,0 +1,54 @@
+use super::Catalog;
+
+pub const CATALOG: Catalog = Catalog {
+    date_format: "%d/%m/%Y %H:%M:%S %Z",
+    date_small_format: "%d/%m/%Y a las %H:%M %Z",
+
+    new_title: "Nuevo incidente",
+    escalated_title: "Incidente escalado",
+    resolved_title: "Incidente resuelto",
+    reminder_title: "Incidente aún activo",
+    new_summary: "Acaba de comenzar un nuevo incidente en {hostname}. Fue provocado por {alert} y su gravedad actual es: {severity}.",
+    escalated_summary: "El incidente en {hostname} ha pasado a un nivel de gravedad superior: {severity}.",
+    resolved_summary: "El incidente en {hostname} provocado por {alert} se ha resuelto.",
+    reminder_summary: "El incidente en {hostname} provocado por {alert} sigue activo ({severity}) y nadie lo ha reconocido.",
+
+    greeting: "¡Hola!",
+    new_intro: "Acaba de comenzar un nuevo incidente. Fue provocado por {alert} y su gravedad actual es: {severity}.",
+    escalated_intro: "El incidente ha pasado a un nivel de gravedad superior: {severity}.",
+    escalated_advice: "Es recomendable actuar de inmediato para resolver este problema.",
+    resolved_intro: "El incidente provocado por {alert} se ha resuelto.",
+    reminder_intro: "El incidente provocado por {alert} sigue activo ({severity}) y nadie lo ha reconocido todavía.",
+    details: "A continuación puede ver los detalles del incidente, incluidos los ajustes y umbrales de la alerta.",
+    see_details: "ver detalles",
+    having_trouble: "¿Algún problema?",
+
+    lookup: "Consulta",
+    result: "Resultado",
+    warning: "Advertencia",
+    critical: "Crítico",
+    severity: "Gravedad",
+    started_at: "Comenzó el",
+    latest_update: "Última actualización",
+    resolved_at: "Resuelto el",
+    at: "El",
+    on: "en",
+    trend: "Tendencia de los últimos resultados",
+
+    digest_title: "{count} incidentes notificados",
+    digest_intro: "Se notificaron varios incidentes en poco tiempo, se agrupan en este resumen en lugar de enviarse uno por uno.",
+    digest_details: "A continuación puede ver los detalles de cada incidente, en el orden en que se notificaron.",
+    digest_sent_at: "Resumen enviado el",
+    digest_subject: "{group} [{count} incidentes] - {date}",
+
+    suppressed_subject: "Speculare: {count} notificaciones suprimidas",
+    suppressed_text: "Se suprimieron {count} notificaciones porque se alcanzó el límite de envío, consulte su panel para ver los detalles.",
+
+    acknowledge: "Reconocer",
+    ack_title: "Reconocer el incidente",
+    ack_confirm: "Las escalaciones del incidente {alert} en {hostname} ya no se notificarán, se sigue supervisando hasta que se resuelva.",
+    ack_by: "Su nombre o correo electrónico",
+    ack_done: "El incidente {alert} en {hostname} fue reconocido por {by} el {date}.",
+    ack_resolved: "El incidente {alert} en {hostname} ya está resuelto.",
+    ack_invalid: "Este enlace no es válido o ha caducado.",
+};
@@ -0,0 +1,54 @@
+use super::Catalog;
+
+pub const CATALOG: Catalog = Catalog {
+    date_format: "%d/%m/%Y %H:%M:%S %Z",
+    date_small_format: "%d/%m/%Y à %H:%M %Z",
+
+    new_title: "Nouvel incident",
+    escalated_title: "Incident aggravé",
+    resolved_title: "Incident résolu",
+    reminder_title: "Incident toujours actif",
+    new_summary: "Un nouvel incident vient de démarrer sur {hostname}. Il a été déclenché par {alert} et sa sévérité actuelle est : {severity}.",
+    escalated_summary: "L'incident sur {hostname} est passé à un niveau de sévérité supérieur : {severity}.",
+    resolved_summary: "L'incident sur {hostname} déclenché par {alert} a été résolu.",
+    reminder_summary: "L'incident sur {hostname} déclenché par {alert} est toujours actif ({severity}) et personne ne l'a pris en charge.",
+
+    greeting: "Bonjour !",
+    new_intro: "Un nouvel incident vient de démarrer. Il a été déclenché par {alert} et sa sévérité actuelle est : {severity}.",
+    escalated_intro: "L'incident est passé à un niveau de sévérité supérieur : {severity}.",
+    escalated_advice: "Il est conseillé d'agir immédiatement pour résoudre ce problème.",
+    resolved_intro: "L'incident déclenché par {alert} a été résolu.",
+    reminder_intro: "L'incident déclenché par {alert} est toujours actif ({severity}) et personne ne l'a encore pris en charge.",
+    details: "Vous trouverez ci-dessous les détails de l'incident, dont les paramètres et les seuils de l'alerte.",
+    see_details: "voir les détails",
+    having_trouble: "Un problème ?",
+
+    lookup: "Requête",
+    result: "Résultat",
+    warning: "Avertissement",
+    critical: "Critique",
+    severity: "Sévérité",
+    started_at: "Début",
+    latest_update: "Dernière mise à jour",
+    resolved_at: "Résolu le",
+    at: "Le",
+    on: "sur",
+    trend: "Tendance des derniers résultats",
+
+    digest_title: "{count} incidents signalés",
+    digest_intro: "Plusieurs incidents ont été signalés en peu de temps, ils sont regroupés dans ce résumé au lieu d'être envoyés un par un.",
+    digest_details: "Vous trouverez ci-dessous les détails de chaque incident, dans l'ordre où ils ont été signalés.",
+    digest_sent_at: "Résumé envoyé le",
+    digest_subject: "{group} [{count} incidents] - {date}",
+
+    suppressed_subject: "Speculare : {count} notifications supprimées",
+    suppressed_text: "{count} notifications ont été supprimées car la limite d'envoi a été atteinte, consultez votre tableau de bord pour les détails.",
+
+    acknowledge: "Prendre en charge",
+    ack_title: "Prendre en charge l'incident",
+    ack_confirm: "Les aggravations de l'incident {alert} sur {hostname} ne seront plus notifiées, il reste surveillé jusqu'à sa résolution.",
+    ack_by: "Votre nom ou e-mail",
+    ack_done: "L'incident {alert} sur {hostname} a été pris en charge par {by} le {date}.",
+    ack_resolved: "L'incident {alert} sur {hostname} est déjà résolu.",
+    ack_invalid: "Ce lien est invalide ou a expiré.",
+};
@@ -0,0 +1,183 @@
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use lettre::message::Mailbox;
+use serde::{Deserialize, Serialize};
+
+use super::EventKind;
+use crate::{
+    monitoring::Severity,
+    utils::config::{mailbox_deser, opt_tz_deser},
+    CONFIG,
+};
+
+mod de;
+mod en;
+mod es;
+mod fr;
+
+/// Enum representing the languages the notifications can be sent in
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Fr,
+    De,
+    Es,
+}
+
+/// Structure representing the translated strings of the notifications.
+///
+/// The sentences may contain placeholders ({alert}, {severity}, ...) filled using `fill`.
+#[derive(Debug, Serialize)]
+pub struct Catalog {
+    pub date_format: &'static str,
+    pub date_small_format: &'static str,
+
+    pub new_title: &'static str,
+    pub escalated_title: &'static str,
+    pub resolved_title: &'static str,
+    pub reminder_title: &'static str,
+    pub new_summary: &'static str,
+    pub escalated_summary: &'static str,
+    pub resolved_summary: &'static str,
+    pub reminder_summary: &'static str,
+
+    pub greeting: &'static str,
+    pub new_intro: &'static str,
+    pub escalated_intro: &'static str,
+    pub escalated_advice: &'static str,
+    pub resolved_intro: &'static str,
+    pub reminder_intro: &'static str,
+    pub details: &'static str,
+    pub see_details: &'static str,
+    pub having_trouble: &'static str,
+
+    pub lookup: &'static str,
+    pub result: &'static str,
+    pub warning: &'static str,
+    pub critical: &'static str,
+    pub severity: &'static str,
+    pub started_at: &'static str,
+    pub latest_update: &'static str,
+    pub resolved_at: &'static str,
+    pub at: &'static str,
+    pub on: &'static str,
+    pub trend: &'static str,
+
+    pub digest_title: &'static str,
+    pub digest_intro: &'static str,
+    pub digest_details: &'static str,
+    pub digest_sent_at: &'static str,
+    pub digest_subject: &'static str,
+
+    pub suppressed_subject: &'static str,
+    pub suppressed_text: &'static str,
+
+    pub acknowledge: &'static str,
+    pub ack_title: &'static str,
+    pub ack_confirm: &'static str,
+    pub ack_by: &'static str,
+    pub ack_done: &'static str,
+    pub ack_resolved: &'static str,
+    pub ack_invalid: &'static str,
+}
+
+impl Catalog {
+    /// Title of the notification for this kind of Event
+    pub fn title(&self, kind: EventKind) -> &'static str {
+        match kind {
+            EventKind::New => self.new_title,
+            EventKind::Escalated => self.escalated_title,
+            EventKind::Resolved => self.resolved_title,
+            EventKind::Reminder => self.reminder_title,
+        }
+    }
+
+    pub fn severity_name(&self, severity: Severity) -> &'static str {
+        match severity {
+            Severity::Warning => self.warning,
+            Severity::Critical => self.critical,
+        }
+    }
+}
+
+/// Structure representing the language and timezone of a mail receiver, as defined in the Config
+#[derive(Debug, Deserialize, Clone)]
+pub struct ReceiverSettings {
+    #[serde(deserialize_with = "mailbox_deser")]
+    pub email: Mailbox,
+    pub locale: Option<Locale>,
+    #[serde(default, deserialize_with = "opt_tz_deser")]
+    pub timezone: Option<Tz>,
+}
+
+/// Structure representing how a notification is written: its language and the timezone of its dates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lang {
+    pub locale: Locale,
+    pub timezone: Tz,
+}
+
+impl Default for Lang {
+    /// The locale and timezone of the Config, used by the channels and the receivers without settings
+    fn default() -> Self {
+        Self {
+            locale: CONFIG.locale,
+            timezone: CONFIG.timezone,
+        }
+    }
+}
+
+impl Lang {
+    /// Lang of the receiver, falling back to the default one for what it does not define
+    pub fn for_receiver(receiver: &Mailbox) -> Self {
+        let default = Self::default();
+        match CONFIG
+            .receivers
+            .iter()
+            .find(|settings| settings.email.email == receiver.email)
+        {
+            Some(settings) => Self {
+                locale: settings.locale.unwrap_or(default.locale),
+                timezone: settings.timezone.unwrap_or(default.timezone),
+            },
+            None => default,
+        }
+    }
+
+    pub fn catalog(&self) -> &'static Catalog {
+        match self.locale {
+            Locale::En => &en::CATALOG,
+            Locale::Fr => &fr::CATALOG,
+            Locale::De => &de::CATALOG,
+            Locale::Es => &es::CATALOG,
+        }
+    }
+
+    /// Format the date (stored as naive UTC) in the timezone of the Lang
+    pub fn format_date(&self, date: NaiveDateTime) -> String {
+        self.format(date, self.catalog().date_format)
+    }
+
+    /// Format the date (stored as naive UTC) in the timezone of the Lang, in a shorter way
+    pub fn format_small_date(&self, date: NaiveDateTime) -> String {
+        self.format(date, self.catalog().date_small_format)
+    }
+
+    fn format(&self, date: NaiveDateTime, format: &str) -> String {
+        Utc.from_utc_datetime(&date)
+            .with_timezone(&self.timezone)
+            .format(format)
+            .to_string()
+    }
+}
+
+/// Replace the {placeholders} of the sentence by their value
+pub fn fill(sentence: &str, values: &[(&str, &str)]) -> String {
+    values
+        .iter()
+        .fold(sentence.to_owned(), |sentence, (key, value)| {
+            sentence.replace(&format!("{{{}}}", key), value)
+        })
+}
@@ -1,79 +1,126 @@
-use lettre::transport::smtp::authentication::Credentials;
-use lettre::transport::smtp::client::{Tls, TlsParameters};
-use lettre::transport::smtp::PoolConfig;
-use lettre::SmtpTransport;
+use chrono::prelude::Utc;
 use lettre::{
-    message::{header, MultiPart, SinglePart},
-    Message, Transport,
+    message::{header, Attachment, Mailbox, MultiPart, SinglePart},
+    Message,
 };
-use once_cell::sync::Lazy;
 use sailfish::TemplateOnce;
-use sproot::models::{Alerts, Incidents};
+use simd_json::{json, prelude::ValueAsMutObject, OwnedValue};
+use sproot::ConnType;
 
-use crate::{
-    monitoring::{IncidentStatus, Severity},
-    CONFIG,
+use super::{
+    acks, digest, dkim, html_escape,
+    i18n::{fill, Catalog, Lang},
+    routing::Destination,
+    sparkline::{self, SPARKLINE_CID},
+    templates::{self, TemplateError},
+    transport, Event, EventKind, Notifier, NotifierError,
 };
-
-const DATE_SMALL_FORMAT: &str = "%d %b %Y at %H:%M";
-const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
-
-// Lazy static for SmtpTransport used to send mails
-// Build it using rustls and a pool of 16 items.
-static MAILER: Lazy<SmtpTransport> = Lazy::new(|| match get_smtp_transport() {
-    Ok(smtp) => smtp,
-    Err(e) => {
-        error!("MAILER: cannot get the smtp_transport: {}", e);
-        std::process::exit(1);
-    }
-});
-
-pub fn test_smtp_transport() {
-    // Check if the SMTP server host is "ok"
-    match MAILER.test_connection() {
-        Ok(result) => {
-            info!("MAILER: No fatal error, connect is: {}", result);
-        }
-        Err(e) => {
-            error!("MAILER: test of the smtp_transport failed: {}", e);
-            std::process::exit(1);
-        }
-    }
-}
+use crate::{monitoring::Severity, CONFIG};
 
 /// Structure representing the incident (created) template html sent by mail
 #[derive(TemplateOnce)]
 #[template(path = "incident.stpl")]
 struct IncidentTemplate<'a> {
-    alert_name: &'a str,
+    t: &'a Catalog,
+    color: &'a str,
+    hostname: &'a str,
+    intro: &'a str,
+    started_at: &'a str,
+    lookup: &'a str,
+    result: &'a str,
+    warn: &'a str,
+    crit: &'a str,
+    sparkline: bool,
+    ack_url: Option<&'a str>,
+}
+
+/// Structure representing the incident (created) template text sent by mail
+#[derive(TemplateOnce)]
+#[template(path = "incident.txt.stpl")]
+struct IncidentTextTemplate<'a> {
+    t: &'a Catalog,
     hostname: &'a str,
-    severity: &'a str,
+    intro: &'a str,
     started_at: &'a str,
     lookup: &'a str,
     result: &'a str,
     warn: &'a str,
     crit: &'a str,
+    ack_url: Option<&'a str>,
 }
 
 /// Structure representing the incident (escalated) template html sent by mail
 #[derive(TemplateOnce)]
 #[template(path = "escalate.stpl")]
 struct EscalateTemplate<'a> {
+    t: &'a Catalog,
+    color: &'a str,
+    hostname: &'a str,
+    intro: &'a str,
+    updated_at: &'a str,
+    lookup: &'a str,
+    result: &'a str,
+    warn: &'a str,
+    crit: &'a str,
+    sparkline: bool,
+    ack_url: Option<&'a str>,
+}
+
+/// Structure representing the incident (escalated) template text sent by mail
+#[derive(TemplateOnce)]
+#[template(path = "escalate.txt.stpl")]
+struct EscalateTextTemplate<'a> {
+    t: &'a Catalog,
+    hostname: &'a str,
+    intro: &'a str,
+    updated_at: &'a str,
+    lookup: &'a str,
+    result: &'a str,
+    warn: &'a str,
+    crit: &'a str,
+    ack_url: Option<&'a str>,
+}
+
+/// Structure representing the incident (reminder) template html sent by mail
+#[derive(TemplateOnce)]
+#[template(path = "reminder.stpl")]
+struct ReminderTemplate<'a> {
+    t: &'a Catalog,
+    color: &'a str,
     hostname: &'a str,
-    severity: &'a str,
+    intro: &'a str,
     updated_at: &'a str,
     lookup: &'a str,
     result: &'a str,
     warn: &'a str,
     crit: &'a str,
+    sparkline: bool,
+    ack_url: Option<&'a str>,
+}
+
+/// Structure representing the incident (reminder) template text sent by mail
+#[derive(TemplateOnce)]
+#[template(path = "reminder.txt.stpl")]
+struct ReminderTextTemplate<'a> {
+    t: &'a Catalog,
+    hostname: &'a str,
+    intro: &'a str,
+    updated_at: &'a str,
+    lookup: &'a str,
+    result: &'a str,
+    warn: &'a str,
+    crit: &'a str,
+    ack_url: Option<&'a str>,
 }
 
 /// Structure representing the incident (resolved) template html sent by mail
 #[derive(TemplateOnce)]
 #[template(path = "resolved.stpl")]
 struct ResolvedTemplate<'a> {
-    alert_name: &'a str,
+    t: &'a Catalog,
+    color: &'a str,
     hostname: &'a str,
+    intro: &'a str,
     resolved_at: &'a str,
     lookup: &'a str,
     result: &'a str,
@@ -81,107 +128,583 @@ struct ResolvedTemplate<'a> {
     crit: &'a str,
 }
 
-/// Send an email alerting on the status (new/escalated/resolved) of an incident.
-pub fn send_information_mail(alert: &Alerts, incident: &Incidents, escalate: bool) {
-    // SAFETY: render_once() can never fails except if called from the template itself.
-    let mail_content = match (escalate, IncidentStatus::from(incident.status)) {
-        (true, _) => EscalateTemplate {
-            hostname: &incident.hostname,
-            severity: &Severity::from(incident.severity).to_string(),
-            updated_at: &incident.updated_at.format(DATE_FORMAT).to_string(),
-            lookup: &alert.lookup,
-            result: &incident.result,
-            warn: &alert.warn,
-            crit: &alert.crit,
+/// Structure representing the incident (resolved) template text sent by mail
+#[derive(TemplateOnce)]
+#[template(path = "resolved.txt.stpl")]
+struct ResolvedTextTemplate<'a> {
+    t: &'a Catalog,
+    hostname: &'a str,
+    intro: &'a str,
+    resolved_at: &'a str,
+    lookup: &'a str,
+    result: &'a str,
+    warn: &'a str,
+    crit: &'a str,
+}
+
+/// Structure representing the body of a mail, its html part being optional
+#[derive(Debug, Clone)]
+pub struct MailContent {
+    pub html: Option<String>,
+    pub text: String,
+    /// PNG sparkline, attached inline to the html part as "cid:sparkline"
+    pub sparkline: Option<Vec<u8>>,
+}
+
+/// Structure identifying the place of a mail in the thread of its incident
+#[derive(Debug, Clone, Copy)]
+pub struct MailThread {
+    pub incident_id: i32,
+    pub kind: EventKind,
+}
+
+impl MailThread {
+    fn new(event: &Event) -> Self {
+        Self {
+            incident_id: event.incident.id,
+            kind: event.kind,
+        }
+    }
+
+    /// Message-ID of the first mail of the incident, which every following mail refers to
+    fn root_id(&self, domain: &str) -> String {
+        format!("<incident-{}@{}>", self.incident_id, domain)
+    }
+
+    /// Message-ID of this mail, the root one for a new incident or a unique one otherwise
+    fn message_id(&self, domain: &str) -> String {
+        match self.kind {
+            EventKind::New => self.root_id(domain),
+            _ => format!(
+                "<incident-{}-{}-{}@{}>",
+                self.incident_id,
+                self.kind,
+                Utc::now().timestamp_millis(),
+                domain
+            ),
+        }
+    }
+}
+
+/// Notifier sending the incidents by mail through the configured transport
+pub struct MailNotifier;
+
+impl Notifier for MailNotifier {
+    fn name(&self) -> &'static str {
+        "SMTP"
+    }
+
+    fn accepts(&self, event: &Event) -> bool {
+        event.destination.allows(self.name()) && !event.destination.receivers.is_empty()
+    }
+
+    fn uses_receivers(&self) -> bool {
+        true
+    }
+
+    /// One part by language, as each one is a separate mail, or by receiver in digest
+    /// mode so that each entry is completed with the digest of its receiver.
+    fn split(&self, destination: &Destination) -> Vec<Destination> {
+        let parts = match CONFIG.digest_window {
+            Some(_) => destination
+                .receivers
+                .iter()
+                .map(|receiver| vec![receiver.clone()])
+                .collect(),
+            None => lang_groups(&destination.receivers)
+                .into_iter()
+                .map(|(_, receivers)| receivers)
+                .collect::<Vec<_>>(),
+        };
+
+        parts
+            .into_iter()
+            .map(|receivers| Destination {
+                receivers,
+                ..destination.clone()
+            })
+            .collect()
+    }
+
+    fn defers(&self) -> bool {
+        CONFIG.digest_window.is_some()
+    }
+
+    fn notify(&self, conn: &mut ConnType, event: &Event) -> Result<(), NotifierError> {
+        let thread = MailThread::new(event);
+
+        let mut failed = Vec::new();
+        for (lang, receivers) in lang_groups(&event.destination.receivers) {
+            let data = InformationData::new(conn, event, lang, &receivers);
+            // A failing custom template should not prevent the incident from being notified
+            let content = render_information_mail(&data).unwrap_or_else(|err| {
+                error!("Templates: {}, using the built-in ones", err);
+                let (html, text) = render_builtin(&data);
+                MailContent {
+                    html: Some(html),
+                    text,
+                    sparkline: data.sparkline.clone(),
+                }
+            });
+            let subject = information_subject(event, &lang);
+
+            // In digest mode the mail is only sent once the aggregation window is over,
+            // unless it has no outbox entry to keep it until then.
+            if let (Some(_), Some(id)) = (CONFIG.digest_window, event.outbox_id) {
+                digest::push(event, id, lang, &receivers, subject, content, thread);
+                continue;
+            }
+
+            if let Err(err) = send_mail(&sender(event), &receivers, subject, content, Some(thread))
+            {
+                failed.push(err.to_string());
+            }
         }
-        .render_once()
-        .unwrap(),
-        (false, IncidentStatus::Active) => IncidentTemplate {
-            alert_name: &alert.name,
-            hostname: &incident.hostname,
-            severity: &Severity::from(incident.severity).to_string(),
-            started_at: &incident.started_at.format(DATE_FORMAT).to_string(),
-            lookup: &alert.lookup,
-            result: &incident.result,
-            warn: &alert.warn,
-            crit: &alert.crit,
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(NotifierError(failed.join(", ")))
         }
-        .render_once()
-        .unwrap(),
-        (false, IncidentStatus::Resolved) => ResolvedTemplate {
-            alert_name: &alert.name,
-            hostname: &incident.hostname,
-            resolved_at: &incident.updated_at.format(DATE_FORMAT).to_string(),
-            lookup: &alert.lookup,
-            result: &incident.result,
-            warn: &alert.warn,
-            crit: &alert.crit,
+    }
+}
+
+/// Group the receivers by Lang, each one getting the mail in its own language and timezone
+fn lang_groups(receivers: &[Mailbox]) -> Vec<(Lang, Vec<Mailbox>)> {
+    let mut groups: Vec<(Lang, Vec<Mailbox>)> = Vec::new();
+    for receiver in receivers {
+        let lang = Lang::for_receiver(receiver);
+        match groups.iter_mut().find(|(l, _)| *l == lang) {
+            Some((_, receivers)) => receivers.push(receiver.clone()),
+            None => groups.push((lang, vec![receiver.clone()])),
         }
-        .render_once()
-        .unwrap(),
-    };
+    }
+    groups
+}
 
-    send_mail(alert, incident, mail_content);
+/// Sender of the mails of the Event, the one of its tenant or the default one
+fn sender(event: &Event) -> Mailbox {
+    event
+        .destination
+        .sender
+        .clone()
+        .unwrap_or_else(|| CONFIG.smtp_email_sender.clone())
 }
 
-fn send_mail(alert: &Alerts, incident: &Incidents, template: String) {
-    // Build the email with all params
-    let email = match Message::builder()
-        // Sender is the email of the sender, which is used by the SMTP
-        // if the sender is not equals to the smtp server account, the mail will ends in the spam.
-        .from(CONFIG.smtp_email_sender.clone())
-        // Receiver is the person who should get the email
-        .to(CONFIG.smtp_email_receiver.clone())
-        // Subject will looks like: "Hostname [alert_name] - 23 Jul 2021 at 17:51"
-        .subject(format!("{} [{}] - {}", incident.hostname, alert.name, incident.started_at.format(DATE_SMALL_FORMAT)))
-        .multipart(
-                // Use multipart to have a fallback
-            MultiPart::alternative()
-                    // This singlepart is the fallback for the html code
-                    .singlepart(
-                        SinglePart::builder()
-                        .header(header::ContentType::TEXT_PLAIN)
-                        .body(String::from("There's a new error being reported by Speculare.\nAllow this mail to be displayed as HTML or go to your dashboard."))
-                    )
-                    // This singlepart is the html design with all fields replaced
-                    .singlepart(
-                        SinglePart::builder()
-                        .header(header::ContentType::TEXT_HTML)
-                        .body(template)
-                    )
-        ) {
-			Ok(mail) => mail,
-			Err(err) => {
-				error!("Could not construct the email: {}", err);
-				return;
-			},
-		};
-
-    // Send the email
-    match MAILER.send(&email) {
-        Ok(_) => info!(
-            "Email for alert {} with host {:.6} sent successfully!",
-            alert.name, incident.host_uuid
+/// Subject will looks like: "Hostname [alert_name] - 23 Jul 2021 at 17:51 UTC"
+///
+/// It only depends on the start of the incident, so every mail of its thread has the same one.
+fn information_subject(event: &Event, lang: &Lang) -> String {
+    format!(
+        "{} [{}] - {}",
+        event.incident.hostname,
+        event.alert.name,
+        lang.format_small_date(event.incident.started_at)
+    )
+}
+
+/// Structure holding the values displayed in the mail of an Event
+pub struct InformationData {
+    kind: EventKind,
+    lang: Lang,
+    alert_name: String,
+    hostname: String,
+    severity: String,
+    color: &'static str,
+    date: String,
+    lookup: String,
+    result: String,
+    warn: String,
+    crit: String,
+    sparkline: Option<Vec<u8>>,
+    ack_url: Option<String>,
+}
+
+impl InformationData {
+    fn new(conn: &mut ConnType, event: &Event, lang: Lang, receivers: &[Mailbox]) -> Self {
+        let (alert, incident) = (event.alert, event.incident);
+        let severity = Severity::from(incident.severity);
+        let date = match event.kind {
+            EventKind::New => incident.started_at,
+            _ => incident.updated_at,
+        };
+        // The trend is only useful while the incident is still ongoing
+        let sparkline = match event.kind {
+            EventKind::Resolved => None,
+            _ => sparkline::render(conn, alert),
+        };
+        // The form of the link is prefilled with the receiver when there's only one
+        let ack_url = match (event.kind, receivers) {
+            (EventKind::Resolved, _) => None,
+            _ if !acks::enabled() => None,
+            (_, [receiver]) => acks::link(incident.id, Some(receiver.email.as_ref())),
+            _ => acks::link(incident.id, None),
+        };
+
+        Self {
+            kind: event.kind,
+            lang,
+            alert_name: alert.name.to_owned(),
+            hostname: incident.hostname.to_owned(),
+            severity: lang.catalog().severity_name(severity).to_owned(),
+            color: event.kind.color(severity),
+            date: lang.format_date(date),
+            lookup: alert.lookup.to_owned(),
+            result: incident.result.to_owned(),
+            warn: alert.warn.to_owned(),
+            crit: alert.crit.to_owned(),
+            sparkline,
+            ack_url,
+        }
+    }
+
+    /// Sample data used to check and preview the templates
+    fn sample(kind: EventKind) -> Self {
+        let lang = Lang::default();
+        let severity = match kind {
+            EventKind::New => Severity::Warning,
+            _ => Severity::Critical,
+        };
+        let points: Vec<f64> = (0..30)
+            .map(|i| 60.0 + i as f64 + 4.0 * (i as f64 / 2.0).sin())
+            .collect();
+
+        Self {
+            kind,
+            lang,
+            alert_name: "disk_usage".to_owned(),
+            hostname: "server-01".to_owned(),
+            severity: lang.catalog().severity_name(severity).to_owned(),
+            color: kind.color(severity),
+            date: lang.format_date(Utc::now().naive_utc()),
+            lookup: "average pct disk.used of disk_io over 5m".to_owned(),
+            result: if kind == EventKind::Resolved {
+                "41.07"
+            } else {
+                "92.41"
+            }
+            .to_owned(),
+            warn: "$this > 80".to_owned(),
+            crit: "$this > 90".to_owned(),
+            sparkline: match kind {
+                EventKind::Resolved => None,
+                _ => sparkline::render_points(&points, Some(80.0), Some(90.0)),
+            },
+            ack_url: match kind {
+                EventKind::Resolved => None,
+                _ => Some("https://alerts.speculare.cloud/ack?t=sample&s=sample".to_owned()),
+            },
+        }
+    }
+
+    /// Name of the templates (without extension) used for this kind of Event
+    fn template_name(&self) -> &'static str {
+        match self.kind {
+            EventKind::New => "incident",
+            EventKind::Escalated => "escalate",
+            EventKind::Resolved => "resolved",
+            EventKind::Reminder => "reminder",
+        }
+    }
+
+    /// First sentence of the mail, with the alert name and the severity highlighted in html
+    fn intro(&self, html: bool) -> String {
+        let catalog = self.lang.catalog();
+        let sentence = match self.kind {
+            EventKind::New => catalog.new_intro,
+            EventKind::Escalated => catalog.escalated_intro,
+            EventKind::Resolved => catalog.resolved_intro,
+            EventKind::Reminder => catalog.reminder_intro,
+        };
+
+        if !html {
+            return fill(
+                sentence,
+                &[("alert", &self.alert_name), ("severity", &self.severity)],
+            );
+        }
+        fill(
+            &html_escape(sentence),
+            &[
+                (
+                    "alert",
+                    &format!(
+                        "<span style=\"padding:3px;border-radius:3px;background-color:#3b82f6;color:#fff;\">{}</span>",
+                        html_escape(&self.alert_name)
+                    ),
+                ),
+                (
+                    "severity",
+                    &format!("<strong>{}</strong>", html_escape(&self.severity)),
+                ),
+            ],
+        )
+    }
+
+    /// Values given to the templates loaded at runtime, named as in the built-in ones
+    fn context(&self) -> OwnedValue {
+        let date_field = match self.kind {
+            EventKind::New => "started_at",
+            EventKind::Escalated | EventKind::Reminder => "updated_at",
+            EventKind::Resolved => "resolved_at",
+        };
+
+        let mut ctx = json!({
+            "alert_name": self.alert_name.to_owned(),
+            "hostname": self.hostname.to_owned(),
+            "severity": self.severity.to_owned(),
+            "color": self.color,
+            "intro": self.intro(false),
+            "lookup": self.lookup.to_owned(),
+            "result": self.result.to_owned(),
+            "warn": self.warn.to_owned(),
+            "crit": self.crit.to_owned(),
+            "ack_url": self.ack_url.to_owned().unwrap_or_default(),
+            "sparkline": self.sparkline.is_some(),
+        });
+        if let Some(obj) = ctx.as_object_mut() {
+            obj.insert(date_field.into(), self.date.to_owned().into());
+            // SAFETY: the Catalog only contains strings, it can always be converted
+            obj.insert(
+                "t".into(),
+                simd_json::serde::to_owned_value(self.lang.catalog()).unwrap(),
+            );
+        }
+        ctx
+    }
+}
+
+/// Render the built-in (html, text) templates for the Event.
+fn render_builtin(data: &InformationData) -> (String, String) {
+    let t = data.lang.catalog();
+    let (intro_html, intro_text) = (data.intro(true), data.intro(false));
+    // SAFETY: render_once() can never fails except if called from the template itself.
+    match data.kind {
+        EventKind::Escalated => (
+            EscalateTemplate {
+                t,
+                color: data.color,
+                hostname: &data.hostname,
+                intro: &intro_html,
+                updated_at: &data.date,
+                lookup: &data.lookup,
+                result: &data.result,
+                warn: &data.warn,
+                crit: &data.crit,
+                sparkline: data.sparkline.is_some(),
+                ack_url: data.ack_url.as_deref(),
+            }
+            .render_once()
+            .unwrap(),
+            EscalateTextTemplate {
+                t,
+                hostname: &data.hostname,
+                intro: &intro_text,
+                updated_at: &data.date,
+                lookup: &data.lookup,
+                result: &data.result,
+                warn: &data.warn,
+                crit: &data.crit,
+                ack_url: data.ack_url.as_deref(),
+            }
+            .render_once()
+            .unwrap(),
+        ),
+        EventKind::Reminder => (
+            ReminderTemplate {
+                t,
+                color: data.color,
+                hostname: &data.hostname,
+                intro: &intro_html,
+                updated_at: &data.date,
+                lookup: &data.lookup,
+                result: &data.result,
+                warn: &data.warn,
+                crit: &data.crit,
+                sparkline: data.sparkline.is_some(),
+                ack_url: data.ack_url.as_deref(),
+            }
+            .render_once()
+            .unwrap(),
+            ReminderTextTemplate {
+                t,
+                hostname: &data.hostname,
+                intro: &intro_text,
+                updated_at: &data.date,
+                lookup: &data.lookup,
+                result: &data.result,
+                warn: &data.warn,
+                crit: &data.crit,
+                ack_url: data.ack_url.as_deref(),
+            }
+            .render_once()
+            .unwrap(),
+        ),
+        EventKind::New => (
+            IncidentTemplate {
+                t,
+                color: data.color,
+                hostname: &data.hostname,
+                intro: &intro_html,
+                started_at: &data.date,
+                lookup: &data.lookup,
+                result: &data.result,
+                warn: &data.warn,
+                crit: &data.crit,
+                sparkline: data.sparkline.is_some(),
+                ack_url: data.ack_url.as_deref(),
+            }
+            .render_once()
+            .unwrap(),
+            IncidentTextTemplate {
+                t,
+                hostname: &data.hostname,
+                intro: &intro_text,
+                started_at: &data.date,
+                lookup: &data.lookup,
+                result: &data.result,
+                warn: &data.warn,
+                crit: &data.crit,
+                ack_url: data.ack_url.as_deref(),
+            }
+            .render_once()
+            .unwrap(),
+        ),
+        EventKind::Resolved => (
+            ResolvedTemplate {
+                t,
+                color: data.color,
+                hostname: &data.hostname,
+                intro: &intro_html,
+                resolved_at: &data.date,
+                lookup: &data.lookup,
+                result: &data.result,
+                warn: &data.warn,
+                crit: &data.crit,
+            }
+            .render_once()
+            .unwrap(),
+            ResolvedTextTemplate {
+                t,
+                hostname: &data.hostname,
+                intro: &intro_text,
+                resolved_at: &data.date,
+                lookup: &data.lookup,
+                result: &data.result,
+                warn: &data.warn,
+                crit: &data.crit,
+            }
+            .render_once()
+            .unwrap(),
         ),
-        Err(err) => error!("Could not send email: {}", err),
     }
 }
 
-fn get_smtp_transport() -> Result<SmtpTransport, lettre::transport::smtp::Error> {
-    let creds = Credentials::new(CONFIG.smtp_user.to_owned(), CONFIG.smtp_password.to_owned());
+/// Render the mail (html and text) alerting on the status (new/escalated/resolved) of an incident.
+///
+/// The templates loaded from the templates_dir are used if any, the built-in ones otherwise.
+/// The sparkline is only attached if the html refers to it, as "cid:sparkline".
+fn render_information_mail(data: &InformationData) -> Result<MailContent, TemplateError> {
+    let ctx = data.context();
+    let name = data.template_name();
+    let html = templates::render(&format!("{}.html", name), &ctx).transpose()?;
+    let text = templates::render(&format!("{}.txt", name), &ctx).transpose()?;
+
+    let (html, text) = match (html, text) {
+        (Some(html), Some(text)) => (html, text),
+        (html, text) => {
+            let (builtin_html, builtin_text) = render_builtin(data);
+            (html.unwrap_or(builtin_html), text.unwrap_or(builtin_text))
+        }
+    };
+
+    let sparkline = data
+        .sparkline
+        .clone()
+        .filter(|_| html.contains(&format!("cid:{}", SPARKLINE_CID)));
+    Ok(MailContent {
+        html: Some(html),
+        text,
+        sparkline,
+    })
+}
+
+/// Render the mail of this kind of Event against sample data.
+pub fn render_sample(kind: EventKind) -> Result<MailContent, TemplateError> {
+    render_information_mail(&InformationData::sample(kind))
+}
+
+pub fn send_mail(
+    sender: &Mailbox,
+    receivers: &[Mailbox],
+    subject: String,
+    content: MailContent,
+    thread: Option<MailThread>,
+) -> Result<(), NotifierError> {
+    // Receivers are the persons who should get the email, resolved by the routing
+    let mut builder = receivers
+        .iter()
+        .fold(Message::builder(), |builder, receiver| {
+            builder.to(receiver.clone())
+        });
 
-    let transport = if CONFIG.smtp_tls {
-        SmtpTransport::builder_dangerous(&CONFIG.smtp_host).tls(Tls::Required(TlsParameters::new(
-            CONFIG.smtp_host.to_owned(),
-        )?))
-    } else {
-        SmtpTransport::builder_dangerous(&CONFIG.smtp_host)
+    // Mails of the same incident are threaded by referring to the Message-ID of the first one
+    if let Some(thread) = thread {
+        let domain = sender.email.domain();
+        builder = builder.message_id(Some(thread.message_id(domain)));
+        if thread.kind != EventKind::New {
+            builder = builder
+                .in_reply_to(thread.root_id(domain))
+                .references(thread.root_id(domain));
+        }
+    }
+
+    // Sender is the email of the sender, which is used by the SMTP
+    // if the sender is not equals to the smtp server account, the mail will ends in the spam.
+    let builder = builder.from(sender.clone()).subject(subject);
+
+    // This singlepart is the text version, used by the clients which can't display html
+    let text = SinglePart::builder()
+        .header(header::ContentType::TEXT_PLAIN)
+        .body(content.text);
+
+    // Build the email with all params
+    let email = match content.html {
+        // Use multipart to have the text as a fallback of the html design
+        Some(html) => {
+            let html = SinglePart::builder()
+                .header(header::ContentType::TEXT_HTML)
+                .body(html);
+            let alternative = MultiPart::alternative().singlepart(text);
+            match content.sparkline {
+                // The sparkline is related to the html part which displays it
+                Some(png) => builder.multipart(alternative.multipart(
+                    MultiPart::related().singlepart(html).singlepart(
+                        Attachment::new_inline(SPARKLINE_CID.to_owned()).body(
+                            png,
+                            // SAFETY: "image/png" is a valid content type
+                            header::ContentType::parse("image/png").unwrap(),
+                        ),
+                    ),
+                )),
+                None => builder.multipart(alternative.singlepart(html)),
+            }
+        }
+        None => builder.singlepart(text),
+    };
+    let mut email = match email {
+        Ok(mail) => mail,
+        Err(err) => {
+            return Err(NotifierError(format!(
+                "could not construct the email: {}",
+                err
+            )))
+        }
     };
 
-    // Open a remote connection to gmail
-    Ok(transport
-        .port(CONFIG.smtp_port)
-        .credentials(creds)
-        .pool_config(PoolConfig::new().max_size(16))
-        .build())
+    // Sign the email so that it passes the DMARC of the receivers' domain
+    dkim::sign(&mut email, sender.email.domain());
+
+    // Send the email using the configured transport
+    transport::send(&email)
 }
@@ -0,0 +1,221 @@
+use chrono::prelude::Utc;
+use simd_json::{json, OwnedValue};
+use sproot::ConnType;
+
+use super::{html_escape, Event, Notifier, NotifierError, AGENT};
+use crate::utils::config::Config;
+
+/// Notifier sending the incidents as m.room.message to a Matrix room
+pub struct MatrixNotifier {
+    homeserver: String,
+    access_token: String,
+    room_id: String,
+}
+
+impl MatrixNotifier {
+    /// Build the MatrixNotifier if the homeserver, token and room are all configured.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        match (
+            &config.matrix_homeserver,
+            &config.matrix_access_token,
+            &config.matrix_room_id,
+        ) {
+            (Some(homeserver), Some(access_token), Some(room_id)) => Some(Self {
+                homeserver: homeserver.trim_end_matches('/').to_owned(),
+                access_token: access_token.to_owned(),
+                room_id: room_id.to_owned(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Build the message with both a plain text body and its html counterpart.
+    fn build_message(event: &Event) -> OwnedValue {
+        let fields = event.fields();
+
+        let body = format!(
+            "{} - {}\n{}\n{}",
+            event.title(),
+            event.incident.hostname,
+            event.summary(),
+            fields
+                .iter()
+                .map(|(label, value)| format!("{}: {}", label, value))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+
+        let formatted_body = format!(
+            "<h4><font color=\"{}\">{}</font> - {}</h4><p>{}</p><ul>{}</ul>",
+            event.color(),
+            event.title(),
+            html_escape(&event.incident.hostname),
+            html_escape(&event.summary()),
+            fields
+                .iter()
+                .map(|(label, value)| format!(
+                    "<li><b>{}</b>: <code>{}</code></li>",
+                    label,
+                    html_escape(value)
+                ))
+                .collect::<String>()
+        );
+
+        json!({
+            "msgtype": "m.text",
+            "body": body,
+            "format": "org.matrix.custom.html",
+            "formatted_body": formatted_body,
+        })
+    }
+}
+
+impl Notifier for MatrixNotifier {
+    fn name(&self) -> &'static str {
+        "Matrix"
+    }
+
+    fn notify(&self, _conn: &mut ConnType, event: &Event) -> Result<(), NotifierError> {
+        self.notify_event(event)
+    }
+
+    fn notify_summary(&self, text: &str) -> Result<(), NotifierError> {
+        let payload = simd_json::to_string(&json!({
+            "msgtype": "m.notice",
+            "body": text,
+        }))
+        .map_err(|err| NotifierError(format!("could not serialize the payload: {}", err)))?;
+
+        let txn_id = format!("speculare-summary-{}", Utc::now().timestamp_millis());
+        self.send(&txn_id, &payload)
+    }
+}
+
+impl MatrixNotifier {
+    /// Send the Event, the database not being needed for Matrix
+    fn notify_event(&self, event: &Event) -> Result<(), NotifierError> {
+        let payload = simd_json::to_string(&Self::build_message(event))
+            .map_err(|err| NotifierError(format!("could not serialize the payload: {}", err)))?;
+
+        // Matrix dedups the messages by transaction id, so that the retries of an
+        // outbox entry use the same one and are only posted once
+        let txn_id = match event.outbox_id {
+            Some(id) => format!("speculare-outbox-{}", id),
+            None => format!(
+                "speculare-{}-{}-{}",
+                event.incident.id,
+                event.kind,
+                Utc::now().timestamp_millis()
+            ),
+        };
+        self.send(&txn_id, &payload)
+    }
+
+    fn send(&self, txn_id: &str, payload: &str) -> Result<(), NotifierError> {
+        let url = format!(
+            "{}/_matrix/client/v3/rooms/{}/send/m.room.message/{}",
+            self.homeserver,
+            url_escape(&self.room_id),
+            txn_id
+        );
+
+        AGENT
+            .put(&url)
+            .set("Content-Type", "application/json")
+            .set("Authorization", &format!("Bearer {}", self.access_token))
+            .send_string(payload)
+            .map(|_| ())
+            .map_err(|err| NotifierError(err.to_string()))
+    }
+}
+
+/// Percent-encode everything but the unreserved characters (room ids contain '!' and ':')
+fn url_escape(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use simd_json::{prelude::*, OwnedValue};
+
+    use super::MatrixNotifier;
+    use crate::notifications::{mock, routing::Destination, Event, EventKind};
+
+    #[test]
+    fn notify() {
+        let (url, received) = mock::serve_once("{\"event_id\": \"$1\"}");
+        let notifier = MatrixNotifier {
+            homeserver: url,
+            access_token: "syt_token".to_owned(),
+            room_id: "!room:example.org".to_owned(),
+        };
+        let (alert, incident) = (mock::alert(), mock::incident(0));
+        let event = Event {
+            kind: EventKind::New,
+            alert: &alert,
+            incident: &incident,
+            destination: &Destination::default(),
+            outbox_id: Some(42),
+        };
+        notifier.notify_event(&event).unwrap();
+
+        let request = received.recv().unwrap();
+        assert_eq!(request.method, "PUT");
+        // The transaction id comes from the outbox entry, so that its retries are deduplicated
+        assert_eq!(
+            request.url,
+            "/_matrix/client/v3/rooms/%21room%3Aexample.org/send/m.room.message/speculare-outbox-42"
+        );
+        assert_eq!(request.authorization.as_deref(), Some("Bearer syt_token"));
+        assert_eq!(request.content_type.as_deref(), Some("application/json"));
+
+        let body: OwnedValue = simd_json::from_slice(&mut request.body.into_bytes()).unwrap();
+        assert_eq!(body.get_str("msgtype"), Some("m.text"));
+        assert_eq!(body.get_str("format"), Some("org.matrix.custom.html"));
+        let text = body.get_str("body").unwrap();
+        assert!(text.starts_with(&format!("{} - web-1\n", event.title())));
+        assert!(text.contains("cpu & <load>"));
+
+        let html = body.get_str("formatted_body").unwrap();
+        // #e7d756, the color of the warning incidents
+        assert!(html.starts_with(&format!(
+            "<h4><font color=\"#e7d756\">{}</font> - web-1</h4>",
+            event.title()
+        )));
+        assert!(html.contains("cpu &amp; &lt;load&gt;"));
+        assert!(!html.contains("<load>"));
+        assert!(html.contains("<code>average pct 10m of user,system over busy</code>"));
+    }
+
+    #[test]
+    fn txn_id_without_outbox() {
+        let (url, received) = mock::serve_once("{}");
+        let notifier = MatrixNotifier {
+            homeserver: url,
+            access_token: "syt_token".to_owned(),
+            room_id: "!room:example.org".to_owned(),
+        };
+        let (alert, incident) = (mock::alert(), mock::incident(1));
+        notifier
+            .notify_event(&Event {
+                kind: EventKind::Resolved,
+                alert: &alert,
+                incident: &incident,
+                destination: &Destination::default(),
+                outbox_id: None,
+            })
+            .unwrap();
+
+        let request = received.recv().unwrap();
+        assert!(request
+            .url
+            .contains("/send/m.room.message/speculare-12-resolved-"));
+    }
+}
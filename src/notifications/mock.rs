@@ -0,0 +1,78 @@
+use std::{sync::mpsc, thread};
+
+use sproot::models::{Alerts, Incidents};
+use tiny_http::{Response, Server};
+
+/// Structure representing the request received by the mock server
+pub struct Received {
+    pub method: String,
+    pub url: String,
+    pub authorization: Option<String>,
+    pub content_type: Option<String>,
+    pub body: String,
+}
+
+/// Start a local HTTP server answering a single request with the body,
+/// return its base url and the request it will receive.
+pub fn serve_once(response: &'static str) -> (String, mpsc::Receiver<Received>) {
+    let server = Server::http("127.0.0.1:0").expect("cannot start the mock server");
+    let port = server
+        .server_addr()
+        .to_ip()
+        .expect("the mock server listens on an ip")
+        .port();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut request = server.recv().expect("cannot receive the request");
+        let header = |name: &'static str| {
+            request
+                .headers()
+                .iter()
+                .find(|h| h.field.equiv(name))
+                .map(|h| h.value.to_string())
+        };
+        let (authorization, content_type) = (header("Authorization"), header("Content-Type"));
+        let mut body = String::new();
+        request.as_reader().read_to_string(&mut body).unwrap();
+
+        tx.send(Received {
+            method: request.method().to_string(),
+            url: request.url().to_owned(),
+            authorization,
+            content_type,
+            body,
+        })
+        .unwrap();
+        let _ = request.respond(Response::from_string(response));
+    });
+
+    (format!("http://127.0.0.1:{}", port), rx)
+}
+
+/// Alert used by the tests, its name having characters to escape
+pub fn alert() -> Alerts {
+    from_json(
+        r#"{"id": 7, "active": true, "name": "cpu & <load>", "table": "cputimes",
+        "lookup": "average pct 10m of user,system over busy", "timing": 60,
+        "warn": "$this > 50", "crit": "$this > 80", "info": null,
+        "host_uuid": "1e9c5c4a-5c1f-4b8e-9c2d-0d2b7f3a9e11", "hostname": "web-1",
+        "where_clause": null, "cid": "3f2504e0-4f89-11d3-9a0c-0305e82c3301"}"#,
+    )
+}
+
+/// Active incident of the alert used by the tests
+pub fn incident(severity: i32) -> Incidents {
+    from_json(&format!(
+        r#"{{"id": 12, "result": "93.5", "started_at": "2026-10-17T08:00:00",
+        "updated_at": "2026-10-17T08:05:00", "resolved_at": null,
+        "host_uuid": "1e9c5c4a-5c1f-4b8e-9c2d-0d2b7f3a9e11", "hostname": "web-1",
+        "status": 0, "severity": {}, "alerts_id": 7,
+        "cid": "3f2504e0-4f89-11d3-9a0c-0305e82c3301"}}"#,
+        severity
+    ))
+}
+
+fn from_json<T: serde::de::DeserializeOwned>(json: &str) -> T {
+    simd_json::from_slice(&mut json.as_bytes().to_vec()).expect("invalid test data")
+}
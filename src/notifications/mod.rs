@@ -1 +1,362 @@
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use sproot::{
+    models::{Alerts, Incidents},
+    ConnType,
+};
+use ureq::{Agent, AgentBuilder};
+
+use self::{
+    i18n::{fill, Lang},
+    routing::Destination,
+};
+use crate::{monitoring::Severity, utils::config::Config, CONFIG};
+
+pub mod acks;
+pub mod delivery;
+pub mod digest;
+pub mod discord;
+pub mod dkim;
+pub mod escalation;
+pub mod i18n;
 pub mod mail;
+pub mod matrix;
+#[cfg(test)]
+pub mod mock;
+pub mod oncall;
+pub mod opsgenie;
+pub mod outbox;
+pub mod pagerduty;
+pub mod ratelimit;
+pub mod routing;
+pub mod slack;
+pub mod smtp;
+pub mod sparkline;
+pub mod templates;
+pub mod tenants;
+pub mod transport;
+pub mod webhook;
+
+// Lazy static for the http Agent used by the http based Notifiers
+// Build it with a timeout so that a slow endpoint does not hang the analysis.
+static AGENT: Lazy<Agent> =
+    Lazy::new(|| AgentBuilder::new().timeout(Duration::from_secs(10)).build());
+
+// Lazy static of the Notifiers built from the Config
+// Every Event is dispatched to each one of them.
+static NOTIFIERS: Lazy<Vec<Box<dyn Notifier>>> = Lazy::new(|| build_notifiers(&CONFIG));
+
+/// Enum representing the transition (new/escalated/resolved) an incident went through,
+/// or the reminder of an incident still active sent by its escalation policy
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    New,
+    Escalated,
+    Resolved,
+    Reminder,
+}
+
+impl std::fmt::Display for EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            EventKind::New => {
+                write!(f, "new")
+            }
+            EventKind::Escalated => {
+                write!(f, "escalated")
+            }
+            EventKind::Resolved => {
+                write!(f, "resolved")
+            }
+            EventKind::Reminder => {
+                write!(f, "reminder")
+            }
+        }
+    }
+}
+
+impl EventKind {
+    /// Hex color of the notification for the severity of the incident
+    pub fn color(&self, severity: Severity) -> &'static str {
+        match (self, severity) {
+            (EventKind::Resolved, _) => "#71c76b",
+            (_, Severity::Warning) => "#e7d756",
+            (_, Severity::Critical) => "#d96f6f",
+        }
+    }
+}
+
+impl From<i32> for EventKind {
+    fn from(v: i32) -> Self {
+        match v {
+            0 => EventKind::New,
+            1 => EventKind::Escalated,
+            3 => EventKind::Reminder,
+            _ => EventKind::Resolved,
+        }
+    }
+}
+
+/// Structure holding everything a Notifier needs to describe an incident
+pub struct Event<'a> {
+    pub kind: EventKind,
+    pub alert: &'a Alerts,
+    pub incident: &'a Incidents,
+    pub destination: &'a Destination,
+    /// Entry of the notification in the outbox, None until it's recorded there
+    pub outbox_id: Option<i64>,
+}
+
+/// The channels are shared by every receiver, so they use the default Lang of the Config
+impl<'a> Event<'a> {
+    /// Title of the notification, same as the one of the mail templates
+    pub fn title(&self) -> &'static str {
+        Lang::default().catalog().title(self.kind)
+    }
+
+    /// Short sentence describing what happened to the incident
+    pub fn summary(&self) -> String {
+        let catalog = Lang::default().catalog();
+        let sentence = match self.kind {
+            EventKind::New => catalog.new_summary,
+            EventKind::Escalated => catalog.escalated_summary,
+            EventKind::Resolved => catalog.resolved_summary,
+            EventKind::Reminder => catalog.reminder_summary,
+        };
+
+        fill(
+            sentence,
+            &[
+                ("hostname", &self.incident.hostname),
+                ("alert", &self.alert.name),
+                (
+                    "severity",
+                    catalog.severity_name(Severity::from(self.incident.severity)),
+                ),
+            ],
+        )
+    }
+
+    /// Details of the incident as (label, value), the same fields the mail templates get
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let lang = Lang::default();
+        let catalog = lang.catalog();
+        let date = match self.kind {
+            EventKind::New => (catalog.started_at, self.incident.started_at),
+            EventKind::Escalated | EventKind::Reminder => {
+                (catalog.latest_update, self.incident.updated_at)
+            }
+            EventKind::Resolved => (catalog.resolved_at, self.incident.updated_at),
+        };
+
+        vec![
+            (catalog.lookup, self.alert.lookup.to_owned()),
+            (catalog.result, self.incident.result.to_owned()),
+            (catalog.warning, self.alert.warn.to_owned()),
+            (catalog.critical, self.alert.crit.to_owned()),
+            (date.0, lang.format_date(date.1)),
+        ]
+    }
+
+    /// Hex color of the notification, same as the bar of the mail templates
+    pub fn color(&self) -> &'static str {
+        self.kind.color(Severity::from(self.incident.severity))
+    }
+}
+
+/// Error returned by a Notifier which could not deliver an Event
+#[derive(Debug)]
+pub struct NotifierError(pub String);
+
+impl std::fmt::Display for NotifierError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Trait implemented by every notification channel (SMTP, webhook, ...)
+pub trait Notifier: Send + Sync {
+    /// Name of the channel, used in the logs
+    fn name(&self) -> &'static str;
+
+    /// Check if this channel should deliver the Event, according to its Destination
+    fn accepts(&self, event: &Event) -> bool {
+        event.destination.allows(self.name())
+    }
+
+    /// Check if this channel sends to the receivers of the Destination (rate limited one by one)
+    fn uses_receivers(&self) -> bool {
+        false
+    }
+
+    /// Split the Destination into the parts this channel delivers separately,
+    /// so that each one has its own outbox entry and a retry does not resend the others.
+    fn split(&self, destination: &Destination) -> Vec<Destination> {
+        vec![destination.clone()]
+    }
+
+    /// Check if this channel only queues the Events (digests), their outbox entries
+    /// being completed once they're really sent instead of after notify.
+    fn defers(&self) -> bool {
+        false
+    }
+
+    /// Deliver the Event through this channel, the connection being there for the
+    /// channels needing more than the Event (history of the alert, ...)
+    fn notify(&self, conn: &mut ConnType, event: &Event) -> Result<(), NotifierError>;
+
+    /// Send a free text summary (of the suppressed notifications) through this channel,
+    /// ignored by the channels which only handle incidents. The mails are summarized
+    /// to their receivers instead, by the rate limit.
+    fn notify_summary(&self, _text: &str) -> Result<(), NotifierError> {
+        Ok(())
+    }
+}
+
+/// Escape the characters having a meaning in html
+pub fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Build the list of Notifiers enabled in the Config.
+///
+/// SMTP is always enabled, the other channels only if they are configured.
+fn build_notifiers(config: &Config) -> Vec<Box<dyn Notifier>> {
+    let mut notifiers: Vec<Box<dyn Notifier>> = vec![Box::new(mail::MailNotifier)];
+
+    if let Some(notifier) = slack::SlackNotifier::from_config(config) {
+        notifiers.push(Box::new(notifier));
+    }
+
+    if let Some(notifier) = discord::DiscordNotifier::from_config(config) {
+        notifiers.push(Box::new(notifier));
+    }
+
+    if let Some(notifier) = matrix::MatrixNotifier::from_config(config) {
+        notifiers.push(Box::new(notifier));
+    }
+
+    if let Some(notifier) = pagerduty::PagerDutyNotifier::from_config(config) {
+        notifiers.push(Box::new(notifier));
+    }
+
+    if let Some(notifier) = opsgenie::OpsgenieNotifier::from_config(config) {
+        notifiers.push(Box::new(notifier));
+    }
+
+    for notifier in webhook::WebhookNotifier::from_config(config) {
+        notifiers.push(Box::new(notifier));
+    }
+
+    notifiers
+}
+
+/// Send the Event through the Notifier and log the result
+fn deliver(
+    conn: &mut ConnType,
+    notifier: &dyn Notifier,
+    event: &Event,
+) -> Result<(), NotifierError> {
+    let res = notifier.notify(conn, event);
+    match &res {
+        Ok(_) => info!(
+            "{}: {} incident for alert {} with host {:.6} sent successfully!",
+            notifier.name(),
+            event.kind,
+            event.alert.name,
+            event.incident.host_uuid
+        ),
+        Err(err) => error!(
+            "{}: could not send the {} incident {}: {}",
+            notifier.name(),
+            event.kind,
+            event.incident.id,
+            err
+        ),
+    }
+    res
+}
+
+/// Send the Event to every Notifier of its Destination, a failing one does not prevent the others.
+///
+/// Each notification goes through the outbox, so that the failed ones are retried later.
+pub fn dispatch(conn: &mut ConnType, kind: EventKind, alert: &Alerts, incident: &Incidents) {
+    // Someone is already on it, no need to page everyone again
+    if kind == EventKind::Escalated {
+        if let Some(ack) = acks::find(conn, incident.id) {
+            info!(
+                "Acks: escalation of incident {} not sent, acknowledged by {}",
+                incident.id, ack.acked_by
+            );
+            return;
+        }
+    }
+
+    let destination = routing::resolve(&CONFIG, alert, incident);
+    // The escalation policy follows the incident from its start to its end, even in the quiet hours
+    match kind {
+        EventKind::New => escalation::schedule(conn, incident, &destination),
+        EventKind::Resolved => escalation::cancel(conn, incident.id),
+        _ => {}
+    }
+
+    let event = Event {
+        kind,
+        alert,
+        incident,
+        destination: &destination,
+        outbox_id: None,
+    };
+    match destination.held_until(incident) {
+        // Held in the outbox, which sends it once the quiet hours are over or the window opens
+        Some(until) => {
+            info!(
+                "Delivery: {} incident for alert {} with host {:.6} held until {} UTC",
+                kind, alert.name, incident.host_uuid, until
+            );
+            for notifier in NOTIFIERS.iter().filter(|n| n.accepts(&event)) {
+                for part in notifier.split(&destination) {
+                    let event = Event {
+                        destination: &part,
+                        ..event
+                    };
+                    outbox::defer(conn, &event, notifier.name(), until);
+                }
+            }
+        }
+        None => send(conn, &event),
+    }
+}
+
+/// Send the Event through every Notifier accepting it, each notification going through the outbox
+fn send(conn: &mut ConnType, event: &Event) {
+    for notifier in NOTIFIERS.iter().filter(|n| n.accepts(event)) {
+        // Drop the receivers or the whole notification if they reached their rate limit
+        let destination = match ratelimit::limit(notifier.as_ref(), event.destination) {
+            Some(destination) => destination,
+            None => continue,
+        };
+
+        for part in notifier.split(&destination) {
+            let mut event = Event {
+                destination: &part,
+                ..*event
+            };
+
+            event.outbox_id = outbox::enqueue(conn, &event, notifier.name());
+            if let (Some(id), true) = (event.outbox_id, notifier.defers()) {
+                outbox::hold(conn, id);
+            }
+            let res = deliver(conn, notifier.as_ref(), &event);
+            if let Some(id) = event.outbox_id {
+                outbox::complete(conn, id, 0, &res);
+            }
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use std::{collections::HashMap, sync::RwLock, time::Duration};
+
+use chrono::{prelude::Utc, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+use diesel::prelude::*;
+use lettre::message::Mailbox;
+use once_cell::sync::Lazy;
+use sproot::{apierrors::ApiError, ConnType, Pool};
+
+use crate::{
+    schema::{oncall_overrides, oncall_schedules},
+    utils::spawn_periodic,
+};
+
+/// Interval at which the schedules are loaded again from the database
+const WORKER_INTERVAL: Duration = Duration::from_secs(60);
+
+// Lazy static of the schedules loaded from the database, by name
+static SCHEDULES: Lazy<RwLock<HashMap<String, Schedule>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Structure representing an on-call schedule, as stored in the database
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = oncall_schedules)]
+struct ScheduleRow {
+    id: i32,
+    name: String,
+    timezone: String,
+    rotation_start: NaiveDateTime,
+    rotation_days: i32,
+    members: Vec<String>,
+}
+
+/// Structure representing a member replacing the one on call for a while
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = oncall_overrides)]
+struct OverrideRow {
+    schedule_id: i32,
+    member: String,
+    starts_at: NaiveDateTime,
+    ends_at: NaiveDateTime,
+}
+
+/// Structure representing an on-call schedule ready to be evaluated.
+///
+/// The members take turns every `rotation_days`, the first one starting at
+/// `rotation_start` (local time of the schedule), so the handoffs happen at the
+/// same local time whatever the DST. Overrides (UTC) take precedence, the last
+/// created one winning if they overlap.
+struct Schedule {
+    timezone: Tz,
+    rotation_start: NaiveDateTime,
+    rotation_days: i32,
+    members: Vec<Mailbox>,
+    overrides: Vec<(NaiveDateTime, NaiveDateTime, Mailbox)>,
+}
+
+impl Schedule {
+    fn on_call(&self, now: NaiveDateTime) -> Option<&Mailbox> {
+        if let Some((_, _, member)) = self
+            .overrides
+            .iter()
+            .rev()
+            .find(|(starts_at, ends_at, _)| *starts_at <= now && now < *ends_at)
+        {
+            return Some(member);
+        }
+
+        if self.members.is_empty() {
+            return None;
+        }
+        let local = Utc
+            .from_utc_datetime(&now)
+            .with_timezone(&self.timezone)
+            .naive_local();
+        let period = i64::from(self.rotation_days.max(1)) * 24 * 3600;
+        let turn = (local - self.rotation_start)
+            .num_seconds()
+            .div_euclid(period);
+        self.members
+            .get(turn.rem_euclid(self.members.len() as i64) as usize)
+    }
+}
+
+/// Member currently on call for the schedule, None if it does not exist or has no member
+pub fn current(name: &str) -> Option<Mailbox> {
+    let schedules = SCHEDULES.read().unwrap();
+    let member = schedules
+        .get(name)
+        .and_then(|schedule| schedule.on_call(Utc::now().naive_utc()))
+        .cloned();
+    if member.is_none() {
+        warn!("On-call: nobody is on call for the schedule {}", name);
+    }
+    member
+}
+
+/// Receivers with the member on call for the schedule (if any) added to them
+pub fn with_on_call(receivers: &[Mailbox], schedule: Option<&String>) -> Vec<Mailbox> {
+    let mut receivers = receivers.to_vec();
+    if let Some(member) = schedule.and_then(|name| current(name)) {
+        if !receivers.contains(&member) {
+            receivers.push(member);
+        }
+    }
+    receivers
+}
+
+fn parse_mailbox(schedule: &str, s: &str) -> Option<Mailbox> {
+    match s.parse() {
+        Ok(mailbox) => Some(mailbox),
+        Err(err) => {
+            error!(
+                "On-call: invalid member \"{}\" in the schedule {}: {}",
+                s, schedule, err
+            );
+            None
+        }
+    }
+}
+
+/// Load the schedules and their current and future overrides
+fn load(conn: &mut ConnType) -> Result<(), ApiError> {
+    let rows: Vec<ScheduleRow> = oncall_schedules::table
+        .select(ScheduleRow::as_select())
+        .load(conn)?;
+    let overrides: Vec<OverrideRow> = oncall_overrides::table
+        .filter(oncall_overrides::ends_at.gt(Utc::now().naive_utc()))
+        .order(oncall_overrides::id)
+        .select(OverrideRow::as_select())
+        .load(conn)?;
+
+    let schedules = rows
+        .into_iter()
+        .map(|row| {
+            let timezone = row.timezone.parse().unwrap_or_else(|err| {
+                error!(
+                    "On-call: invalid timezone \"{}\" for the schedule {}, using UTC: {}",
+                    row.timezone, row.name, err
+                );
+                Tz::UTC
+            });
+            let schedule = Schedule {
+                timezone,
+                rotation_start: row.rotation_start,
+                rotation_days: row.rotation_days,
+                members: row
+                    .members
+                    .iter()
+                    .filter_map(|m| parse_mailbox(&row.name, m))
+                    .collect(),
+                overrides: overrides
+                    .iter()
+                    .filter(|o| o.schedule_id == row.id)
+                    .filter_map(|o| {
+                        parse_mailbox(&row.name, &o.member)
+                            .map(|member| (o.starts_at, o.ends_at, member))
+                    })
+                    .collect(),
+            };
+            (row.name, schedule)
+        })
+        .collect();
+
+    *SCHEDULES.write().unwrap() = schedules;
+    Ok(())
+}
+
+/// Load the schedules before the first incident needs them
+pub fn init(pool: &Pool) -> Result<(), ApiError> {
+    load(&mut pool.get()?)
+}
+
+/// Start the worker loading the schedules again, so that their changes are used without a restart
+pub fn start_worker(pool: Pool) {
+    spawn_periodic("On-call", WORKER_INTERVAL, move || {
+        let res = pool
+            .get()
+            .map_err(ApiError::from)
+            .and_then(|mut conn| load(&mut conn));
+        if let Err(err) = res {
+            error!("On-call: cannot load the schedules: {}", err);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+    use lettre::message::Mailbox;
+
+    use super::Schedule;
+
+    fn mailbox(s: &str) -> Mailbox {
+        s.parse().unwrap()
+    }
+
+    fn date(date: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    /// Weekly rotation handing over on monday at 09:00 in Paris
+    fn schedule() -> Schedule {
+        Schedule {
+            timezone: chrono_tz::Europe::Paris,
+            rotation_start: date("2026-10-12 09:00"),
+            rotation_days: 7,
+            members: vec![
+                mailbox("alice@mail.com"),
+                mailbox("bob@mail.com"),
+                mailbox("carol@mail.com"),
+            ],
+            overrides: Vec::new(),
+        }
+    }
+
+    fn on_call(schedule: &Schedule, now: &str) -> String {
+        schedule.on_call(date(now)).unwrap().email.to_string()
+    }
+
+    #[test]
+    fn rotation_handoff() {
+        let schedule = schedule();
+        assert_eq!(on_call(&schedule, "2026-10-12 07:00"), "alice@mail.com");
+        // 09:00 in Paris is 07:00 UTC before the DST change of 2026-10-25...
+        assert_eq!(on_call(&schedule, "2026-10-19 06:59"), "alice@mail.com");
+        assert_eq!(on_call(&schedule, "2026-10-19 07:00"), "bob@mail.com");
+        // ...and 08:00 UTC after it, the handoff staying at 09:00 local time
+        assert_eq!(on_call(&schedule, "2026-10-26 07:59"), "bob@mail.com");
+        assert_eq!(on_call(&schedule, "2026-10-26 08:00"), "carol@mail.com");
+        // Back to the first member once everyone had its turn
+        assert_eq!(on_call(&schedule, "2026-11-02 08:00"), "alice@mail.com");
+        // Before the start, the rotation goes backward
+        assert_eq!(on_call(&schedule, "2026-10-10 12:00"), "carol@mail.com");
+    }
+
+    #[test]
+    fn overrides() {
+        let mut schedule = schedule();
+        schedule.overrides = vec![
+            (
+                date("2026-10-13 00:00"),
+                date("2026-10-15 00:00"),
+                mailbox("dave@mail.com"),
+            ),
+            (
+                date("2026-10-14 00:00"),
+                date("2026-10-14 12:00"),
+                mailbox("erin@mail.com"),
+            ),
+        ];
+
+        assert_eq!(on_call(&schedule, "2026-10-12 23:59"), "alice@mail.com");
+        assert_eq!(on_call(&schedule, "2026-10-13 00:00"), "dave@mail.com");
+        // The last created override wins when they overlap
+        assert_eq!(on_call(&schedule, "2026-10-14 06:00"), "erin@mail.com");
+        assert_eq!(on_call(&schedule, "2026-10-14 12:00"), "dave@mail.com");
+        assert_eq!(on_call(&schedule, "2026-10-15 00:00"), "alice@mail.com");
+
+        // An override applies even to a schedule without members
+        schedule.members.clear();
+        assert_eq!(on_call(&schedule, "2026-10-13 12:00"), "dave@mail.com");
+        assert!(schedule.on_call(date("2026-10-16 12:00")).is_none());
+    }
+}
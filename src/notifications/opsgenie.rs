@@ -0,0 +1,101 @@
+use simd_json::{json, owned::Object, OwnedValue};
+use sproot::ConnType;
+
+use super::{Event, EventKind, Notifier, NotifierError, AGENT};
+use crate::{monitoring::Severity, utils::config::Config};
+
+/// Notifier mapping the incidents lifecycle onto the Opsgenie Alert API.
+///
+/// The Incidents.id is used as the alias of the Opsgenie alert so that
+/// an escalation raises its priority and a resolve closes it.
+pub struct OpsgenieNotifier {
+    api_url: String,
+    api_key: String,
+}
+
+impl OpsgenieNotifier {
+    /// Build the OpsgenieNotifier if an api key is configured.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        config.opsgenie_api_key.as_ref().map(|key| Self {
+            api_url: config.opsgenie_api_url.trim_end_matches('/').to_owned(),
+            api_key: key.to_owned(),
+        })
+    }
+
+    fn priority(event: &Event) -> &'static str {
+        match Severity::from(event.incident.severity) {
+            Severity::Warning => "P3",
+            Severity::Critical => "P1",
+        }
+    }
+
+    fn send(&self, method: &str, path: &str, body: OwnedValue) -> Result<(), NotifierError> {
+        let payload = simd_json::to_string(&body)
+            .map_err(|err| NotifierError(format!("could not serialize the payload: {}", err)))?;
+
+        AGENT
+            .request(method, &format!("{}/v2/alerts{}", self.api_url, path))
+            .set("Content-Type", "application/json")
+            .set("Authorization", &format!("GenieKey {}", self.api_key))
+            .send_string(&payload)
+            .map(|_| ())
+            .map_err(|err| NotifierError(err.to_string()))
+    }
+}
+
+impl Notifier for OpsgenieNotifier {
+    fn name(&self) -> &'static str {
+        "Opsgenie"
+    }
+
+    fn notify(&self, _conn: &mut ConnType, event: &Event) -> Result<(), NotifierError> {
+        let alias = event.incident.id.to_string();
+
+        match event.kind {
+            EventKind::New => {
+                let details: Object = event
+                    .fields()
+                    .into_iter()
+                    .map(|(label, value)| (label.to_owned(), OwnedValue::from(value)))
+                    .collect();
+
+                self.send(
+                    "POST",
+                    "",
+                    json!({
+                        "message": format!("{} on {}", event.alert.name, event.incident.hostname),
+                        "alias": alias,
+                        "description": event.summary(),
+                        "priority": Self::priority(event),
+                        "entity": event.incident.hostname.to_owned(),
+                        "source": "Speculare",
+                        "details": details,
+                    }),
+                )
+            }
+            // Opsgenie only dedups on create, the priority must be changed explicitly
+            EventKind::Escalated => self.send(
+                "PUT",
+                &format!("/{}/priority?identifierType=alias", alias),
+                json!({ "priority": Self::priority(event) }),
+            ),
+            // The alert is already open, the reminder is added as a note
+            EventKind::Reminder => self.send(
+                "POST",
+                &format!("/{}/notes?identifierType=alias", alias),
+                json!({
+                    "source": "Speculare",
+                    "note": event.summary(),
+                }),
+            ),
+            EventKind::Resolved => self.send(
+                "POST",
+                &format!("/{}/close?identifierType=alias", alias),
+                json!({
+                    "source": "Speculare",
+                    "note": event.summary(),
+                }),
+            ),
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use std::time::Duration;
+
+use chrono::{prelude::Utc, NaiveDateTime};
+use diesel::prelude::*;
+use sproot::{
+    apierrors::ApiError,
+    models::{Alerts, BaseCrud, Incidents},
+    ConnType, Pool,
+};
+
+use super::{
+    deliver, escalation, ratelimit, routing, Event, EventKind, Notifier, NotifierError, NOTIFIERS,
+};
+use crate::{schema::notifications_outbox, utils::spawn_periodic, CONFIG};
+
+/// Delay before the first retry, doubled for each failed attempt
+const BACKOFF_BASE_SECS: i64 = 30;
+/// Maximum delay between two attempts
+const BACKOFF_MAX_SECS: i64 = 3600;
+/// Interval at which the worker looks for the notifications to retry
+const WORKER_INTERVAL: Duration = Duration::from_secs(15);
+/// Maximum number of notifications retried at each tick of the worker
+const WORKER_BATCH: i64 = 100;
+
+/// Enum representing the Status of a notification in the outbox
+///
+/// Sent notifications are removed from the outbox, so they don't have a status.
+/// The ones aggregated in a digest are kept until the digest is sent.
+pub enum OutboxStatus {
+    Pending,
+    Dead,
+    Digest,
+}
+
+/// Structure representing a notification waiting in the outbox (what's needed to retry it)
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = notifications_outbox)]
+pub struct OutboxEntry {
+    pub id: i64,
+    pub incident_id: i32,
+    pub alerts_id: i64,
+    pub kind: i32,
+    pub channel: String,
+    pub receivers: Vec<String>,
+    pub attempts: i32,
+}
+
+/// Structure used to insert a notification in the outbox
+#[derive(Insertable)]
+#[diesel(table_name = notifications_outbox)]
+struct OutboxEntryDTO<'a> {
+    incident_id: i32,
+    alerts_id: i64,
+    kind: i32,
+    channel: &'a str,
+    receivers: Vec<String>,
+    status: i32,
+    attempts: i32,
+    next_attempt_at: NaiveDateTime,
+    created_at: NaiveDateTime,
+}
+
+/// Put back in the queue the notifications of the digests lost with the previous run
+pub fn init(pool: &Pool) -> Result<(), ApiError> {
+    diesel::update(
+        notifications_outbox::table
+            .filter(notifications_outbox::status.eq(OutboxStatus::Digest as i32)),
+    )
+    .set((
+        notifications_outbox::status.eq(OutboxStatus::Pending as i32),
+        notifications_outbox::next_attempt_at.eq(Utc::now().naive_local()),
+    ))
+    .execute(&mut pool.get()?)?;
+    Ok(())
+}
+
+/// Record the notification of the Event through the channel, before trying to send it.
+///
+/// Its receivers are stored with it, so that a retry only reaches the ones it failed to,
+/// not the whole Route again. Return the id of the entry, or None if the outbox could not be written to.
+pub fn enqueue(conn: &mut ConnType, event: &Event, channel: &str) -> Option<i64> {
+    insert(conn, event, channel, Utc::now().naive_local())
+}
+
+/// Record the notification of the Event through the channel, to be sent at the (UTC) date.
+///
+/// Used to hold the notifications outside of the delivery windows.
+pub fn defer(conn: &mut ConnType, event: &Event, channel: &str, until: NaiveDateTime) {
+    insert(conn, event, channel, until);
+}
+
+fn insert(
+    conn: &mut ConnType,
+    event: &Event,
+    channel: &str,
+    next_attempt_at: NaiveDateTime,
+) -> Option<i64> {
+    let now = Utc::now().naive_local();
+    match diesel::insert_into(notifications_outbox::table)
+        .values(&OutboxEntryDTO {
+            incident_id: event.incident.id,
+            alerts_id: event.alert.id,
+            kind: event.kind as i32,
+            channel,
+            receivers: event
+                .destination
+                .receivers
+                .iter()
+                .map(|receiver| receiver.to_string())
+                .collect(),
+            status: OutboxStatus::Pending as i32,
+            attempts: 0,
+            next_attempt_at,
+            created_at: now,
+        })
+        .returning(notifications_outbox::id)
+        .get_result(conn)
+    {
+        Ok(id) => Some(id),
+        Err(err) => {
+            error!("Outbox: could not record the notification: {}", err);
+            None
+        }
+    }
+}
+
+/// Mark the entry as aggregated in a digest, so that it's not retried meanwhile
+pub fn hold(conn: &mut ConnType, id: i64) {
+    if let Err(err) = diesel::update(notifications_outbox::table.find(id))
+        .set(notifications_outbox::status.eq(OutboxStatus::Digest as i32))
+        .execute(conn)
+    {
+        error!("Outbox: could not update the notification {}: {}", id, err);
+    }
+}
+
+/// Update the entry with the result of an attempt.
+///
+/// A sent notification is removed (unless it's held by a digest, which completes it
+/// once sent), a failed one is scheduled again with an exponential backoff, or marked
+/// as Dead once it reached outbox_max_attempts.
+pub fn complete(conn: &mut ConnType, id: i64, attempts: i32, result: &Result<(), NotifierError>) {
+    let res = match result {
+        Ok(_) => diesel::delete(
+            notifications_outbox::table
+                .find(id)
+                .filter(notifications_outbox::status.ne(OutboxStatus::Digest as i32)),
+        )
+        .execute(conn)
+        .map(|_| ()),
+        Err(err) => {
+            let attempts = attempts + 1;
+            let status = if attempts >= CONFIG.outbox_max_attempts {
+                error!(
+                    "Outbox: notification {} failed {} times, giving up",
+                    id, attempts
+                );
+                OutboxStatus::Dead
+            } else {
+                OutboxStatus::Pending
+            };
+
+            diesel::update(notifications_outbox::table.find(id))
+                .set((
+                    notifications_outbox::status.eq(status as i32),
+                    notifications_outbox::attempts.eq(attempts),
+                    notifications_outbox::last_error.eq(err.to_string()),
+                    notifications_outbox::next_attempt_at
+                        .eq(Utc::now().naive_local() + backoff(attempts)),
+                ))
+                .execute(conn)
+                .map(|_| ())
+        }
+    };
+
+    if let Err(err) = res {
+        error!("Outbox: could not update the notification {}: {}", id, err);
+    }
+}
+
+/// Complete the entries aggregated in a digest with the result of its sending
+pub fn complete_digest(conn: &mut ConnType, ids: &[i64], result: &Result<(), NotifierError>) {
+    let res = match result {
+        Ok(_) => {
+            diesel::delete(notifications_outbox::table.filter(notifications_outbox::id.eq_any(ids)))
+                .execute(conn)
+                .map(|_| ())
+        }
+        Err(_) => notifications_outbox::table
+            .filter(notifications_outbox::id.eq_any(ids))
+            .select((notifications_outbox::id, notifications_outbox::attempts))
+            .load::<(i64, i32)>(conn)
+            .map(|entries| {
+                for (id, attempts) in entries {
+                    complete(conn, id, attempts, result);
+                }
+            }),
+    };
+
+    if let Err(err) = res {
+        error!(
+            "Outbox: could not update the notifications of a digest: {}",
+            err
+        );
+    }
+}
+
+/// Delay before the next attempt: 30s, 1m, 2m, ... up to 1h
+fn backoff(attempts: i32) -> chrono::Duration {
+    let exp = (attempts - 1).clamp(0, 16) as u32;
+    chrono::Duration::seconds((BACKOFF_BASE_SECS << exp).min(BACKOFF_MAX_SECS))
+}
+
+/// Try to send again the pending notifications whose next attempt is due
+fn retry_pending(conn: &mut ConnType) -> Result<(), ApiError> {
+    let entries: Vec<OutboxEntry> = notifications_outbox::table
+        .filter(notifications_outbox::status.eq(OutboxStatus::Pending as i32))
+        .filter(notifications_outbox::next_attempt_at.le(Utc::now().naive_local()))
+        .order(notifications_outbox::next_attempt_at)
+        .limit(WORKER_BATCH)
+        .select(OutboxEntry::as_select())
+        .load(conn)?;
+
+    for entry in entries {
+        trace!(
+            "Outbox: retrying notification {} ({} attempts)",
+            entry.id,
+            entry.attempts
+        );
+
+        let result = match NOTIFIERS.iter().find(|n| n.name() == entry.channel) {
+            Some(notifier) => retry_entry(conn, &entry, notifier.as_ref()),
+            None => Err(NotifierError(format!(
+                "channel {} is not configured anymore",
+                entry.channel
+            ))),
+        };
+        complete(conn, entry.id, entry.attempts, &result);
+    }
+
+    Ok(())
+}
+
+fn retry_entry(
+    conn: &mut ConnType,
+    entry: &OutboxEntry,
+    notifier: &dyn Notifier,
+) -> Result<(), NotifierError> {
+    // Get the current state of the incident and alert, as they're not stored in the outbox
+    let incident = Incidents::get_specific(conn, entry.incident_id)
+        .map_err(|err| NotifierError(format!("cannot get the incident: {}", err)))?;
+    let alert = Alerts::get_specific(conn, entry.alerts_id)
+        .map_err(|err| NotifierError(format!("cannot get the alert: {}", err)))?;
+    let kind = EventKind::from(entry.kind);
+    // The reminders were sent to a tier of the escalation policy, not to the Route
+    let mut destination = match kind {
+        EventKind::Reminder => escalation::current_destination(conn, &alert, &incident),
+        _ => None,
+    }
+    .unwrap_or_else(|| routing::resolve(&CONFIG, &alert, &incident));
+    // Only the receivers of the entry, the others were already notified (or rate limited)
+    if notifier.uses_receivers() {
+        destination.receivers = entry
+            .receivers
+            .iter()
+            .filter_map(|receiver| receiver.parse().ok())
+            .collect();
+    }
+    // The held and failed notifications are rate limited like the others when they're sent,
+    // the suppressed ones being reported by the summaries instead of retried.
+    let destination = match ratelimit::limit(notifier, &destination) {
+        Some(destination) => destination,
+        None => {
+            info!(
+                "Outbox: notification {} suppressed by the rate limit",
+                entry.id
+            );
+            return Ok(());
+        }
+    };
+
+    if notifier.defers() {
+        hold(conn, entry.id);
+    }
+    deliver(
+        conn,
+        notifier,
+        &Event {
+            kind,
+            alert: &alert,
+            incident: &incident,
+            destination: &destination,
+            outbox_id: Some(entry.id),
+        },
+    )
+}
+
+/// Start the worker retrying the pending notifications, supervised by Bastion.
+pub fn start_worker(pool: Pool) {
+    spawn_periodic("Outbox", WORKER_INTERVAL, move || {
+        let res = pool
+            .get()
+            .map_err(ApiError::from)
+            .and_then(|mut conn| retry_pending(&mut conn));
+        if let Err(err) = res {
+            error!("Outbox: cannot retry the pending notifications: {}", err);
+        }
+    });
+}
@@ -0,0 +1,85 @@
+use simd_json::{json, owned::Object, OwnedValue};
+use sproot::ConnType;
+
+use super::{Event, EventKind, Notifier, NotifierError, AGENT};
+use crate::{monitoring::Severity, utils::config::Config};
+
+const PAGERDUTY_ENQUEUE: &str = "https://events.pagerduty.com/v2/enqueue";
+
+/// Notifier mapping the incidents lifecycle onto the PagerDuty Events API v2.
+///
+/// The Incidents.id is used as the dedup_key so that an escalation updates
+/// the same PagerDuty alert and a resolve closes it.
+pub struct PagerDutyNotifier {
+    routing_key: String,
+}
+
+impl PagerDutyNotifier {
+    /// Build the PagerDutyNotifier if a routing (integration) key is configured.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        config.pagerduty_routing_key.as_ref().map(|key| Self {
+            routing_key: key.to_owned(),
+        })
+    }
+
+    fn build_event(&self, event: &Event) -> OwnedValue {
+        let dedup_key = event.incident.id.to_string();
+
+        // Resolving only needs the dedup_key, no payload
+        if event.kind == EventKind::Resolved {
+            return json!({
+                "routing_key": self.routing_key.to_owned(),
+                "event_action": "resolve",
+                "dedup_key": dedup_key,
+            });
+        }
+
+        let severity = match Severity::from(event.incident.severity) {
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        };
+        let details: Object = event
+            .fields()
+            .into_iter()
+            .map(|(label, value)| (label.to_owned(), OwnedValue::from(value)))
+            .collect();
+
+        // A new trigger with the same dedup_key updates the existing alert (escalation)
+        json!({
+            "routing_key": self.routing_key.to_owned(),
+            "event_action": "trigger",
+            "dedup_key": dedup_key,
+            "payload": {
+                "summary": format!(
+                    "[{}] {} on {}: {}",
+                    Severity::from(event.incident.severity),
+                    event.alert.name,
+                    event.incident.hostname,
+                    event.incident.result
+                ),
+                "source": event.incident.hostname.to_owned(),
+                "severity": severity,
+                "component": event.alert.name.to_owned(),
+                "custom_details": details,
+            },
+        })
+    }
+}
+
+impl Notifier for PagerDutyNotifier {
+    fn name(&self) -> &'static str {
+        "PagerDuty"
+    }
+
+    fn notify(&self, _conn: &mut ConnType, event: &Event) -> Result<(), NotifierError> {
+        let payload = simd_json::to_string(&self.build_event(event))
+            .map_err(|err| NotifierError(format!("could not serialize the payload: {}", err)))?;
+
+        AGENT
+            .post(PAGERDUTY_ENQUEUE)
+            .set("Content-Type", "application/json")
+            .send_string(&payload)
+            .map(|_| ())
+            .map_err(|err| NotifierError(err.to_string()))
+    }
+}
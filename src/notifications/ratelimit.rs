@@ -0,0 +1,222 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use lettre::message::Mailbox;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+use super::{
+    i18n::{fill, Lang},
+    mail::{self, MailContent},
+    routing::Destination,
+    Notifier, NOTIFIERS,
+};
+use crate::{utils::spawn_periodic, CONFIG};
+
+/// Interval at which the worker looks for the summaries to send
+const WORKER_INTERVAL: Duration = Duration::from_secs(30);
+
+// Lazy static of the token buckets, by channel or receiver
+static BUCKETS: Lazy<Mutex<HashMap<String, Bucket>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Structure representing a rate limit: at most `max` notifications per `period` seconds
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct RateLimit {
+    pub max: u32,
+    pub period: u64,
+}
+
+/// Enum representing who gets the summary of the suppressed notifications
+///
+/// The mails are summarized to the receivers they were meant for (and from their sender),
+/// so that the suppressed mails of a tenant are neither reported to the others nor hidden from it.
+#[derive(Clone, PartialEq)]
+enum Target {
+    Channel(&'static str),
+    Mail {
+        receivers: Vec<Mailbox>,
+        sender: Mailbox,
+    },
+}
+
+/// Structure representing a token bucket, refilled continuously up to `limit.max`
+struct Bucket {
+    limit: RateLimit,
+    tokens: f64,
+    refilled_at: Instant,
+    /// Number of notifications suppressed since the last summary, by Target
+    suppressed: Vec<(Target, u32)>,
+}
+
+impl Bucket {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            tokens: limit.max as f64,
+            refilled_at: Instant::now(),
+            suppressed: Vec::new(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let rate = self.limit.max as f64 / self.limit.period.max(1) as f64;
+        self.tokens = (self.tokens + self.refilled_at.elapsed().as_secs_f64() * rate)
+            .min(self.limit.max as f64);
+        self.refilled_at = Instant::now();
+    }
+
+    /// Check if there's a token left, without taking it
+    fn has_token(&mut self) -> bool {
+        self.refill();
+        self.tokens >= 1.0
+    }
+
+    /// Count a notification for the Target as suppressed
+    fn suppress(&mut self, target: Target) {
+        if self.suppressed.is_empty() {
+            warn!("Rate limit: limit reached, suppressing the next notifications");
+        }
+        match self.suppressed.iter_mut().find(|(t, _)| *t == target) {
+            Some((_, count)) => *count += 1,
+            None => self.suppressed.push((target, 1)),
+        }
+    }
+
+    /// Take a token if there's one left, or count the notification as suppressed
+    fn take(&mut self, target: impl FnOnce() -> Target) -> bool {
+        if self.has_token() {
+            self.tokens -= 1.0;
+            true
+        } else {
+            self.suppress(target());
+            false
+        }
+    }
+}
+
+fn bucket(buckets: &mut HashMap<String, Bucket>, key: String, limit: RateLimit) -> &mut Bucket {
+    buckets.entry(key).or_insert_with(|| Bucket::new(limit))
+}
+
+/// Apply the rate limits of the Config to the Destination of the notifier.
+///
+/// Return the Destination without the rate limited receivers, or None if
+/// the notification must not be sent at all.
+pub fn limit(notifier: &dyn Notifier, destination: &Destination) -> Option<Destination> {
+    let mut destination = destination.clone();
+    let sender = destination
+        .sender
+        .clone()
+        .unwrap_or_else(|| CONFIG.smtp_email_sender.clone());
+    let channel = notifier.name();
+    let mut buckets = BUCKETS.lock().unwrap();
+
+    // The channel is checked first so that a notification it suppresses does not use
+    // the tokens of its receivers, its token being only taken if someone is left to notify.
+    if let Some(limit) = CONFIG.rate_limit_channel {
+        let channel_bucket = bucket(&mut buckets, format!("channel:{}", channel), limit);
+        if !channel_bucket.has_token() {
+            channel_bucket.suppress(match notifier.uses_receivers() {
+                true => Target::Mail {
+                    receivers: destination.receivers.clone(),
+                    sender,
+                },
+                false => Target::Channel(channel),
+            });
+            return None;
+        }
+    }
+
+    if let (Some(limit), true) = (CONFIG.rate_limit_receiver, notifier.uses_receivers()) {
+        destination.receivers.retain(|receiver| {
+            bucket(&mut buckets, format!("receiver:{}", receiver.email), limit).take(|| {
+                Target::Mail {
+                    receivers: vec![receiver.clone()],
+                    sender: sender.clone(),
+                }
+            })
+        });
+        if destination.receivers.is_empty() {
+            return None;
+        }
+    }
+
+    if let Some(limit) = CONFIG.rate_limit_channel {
+        bucket(&mut buckets, format!("channel:{}", channel), limit).tokens -= 1.0;
+    }
+
+    Some(destination)
+}
+
+/// Send a summary for each Target which had notifications suppressed, as long as its bucket has tokens
+fn send_summaries() {
+    let mut ready = Vec::new();
+    {
+        let mut buckets = BUCKETS.lock().unwrap();
+        for bucket in buckets.values_mut() {
+            while !bucket.suppressed.is_empty() && bucket.has_token() {
+                bucket.tokens -= 1.0;
+                ready.push(bucket.suppressed.remove(0));
+            }
+        }
+    }
+
+    for (target, count) in ready {
+        let count_str = count.to_string();
+        let values = [("count", count_str.as_str())];
+
+        let (name, res) = match target {
+            Target::Channel(channel) => (
+                channel.to_owned(),
+                match NOTIFIERS.iter().find(|n| n.name() == channel) {
+                    Some(notifier) => notifier
+                        .notify_summary(&fill(Lang::default().catalog().suppressed_text, &values)),
+                    None => Ok(()),
+                },
+            ),
+            Target::Mail { receivers, sender } => {
+                let catalog = receivers
+                    .first()
+                    .map_or_else(Lang::default, Lang::for_receiver)
+                    .catalog();
+                (
+                    receivers
+                        .iter()
+                        .map(|receiver| receiver.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    mail::send_mail(
+                        &sender,
+                        &receivers,
+                        fill(catalog.suppressed_subject, &values),
+                        MailContent {
+                            html: None,
+                            text: fill(catalog.suppressed_text, &values),
+                            sparkline: None,
+                        },
+                        None,
+                    ),
+                )
+            }
+        };
+
+        match res {
+            Ok(_) => info!(
+                "Rate limit: summary of {} suppressed notifications sent to {}",
+                count, name
+            ),
+            Err(err) => error!(
+                "Rate limit: could not send the summary to {}: {}",
+                name, err
+            ),
+        }
+    }
+}
+
+/// Start the worker sending the summaries, supervised by Bastion.
+pub fn start_worker() {
+    spawn_periodic("Rate limit", WORKER_INTERVAL, send_summaries);
+}
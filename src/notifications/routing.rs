@@ -0,0 +1,209 @@
+use chrono::NaiveDateTime;
+use lettre::message::Mailbox;
+use regex::Regex;
+use serde::Deserialize;
+use sproot::models::{Alerts, Incidents};
+
+use super::{
+    delivery::{self, DeliveryWindow},
+    oncall,
+    tenants::QuietHours,
+};
+use crate::{
+    monitoring::Severity,
+    utils::config::{mailboxes_deser, regex_deser, Config},
+};
+
+/// Structure representing a routing rule, as defined in the Config.
+///
+/// Every criteria which is set must match for the Route to be used,
+/// `alert` and `hostname` are (anchored) regex, the others are exact matches.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Route {
+    #[serde(default, deserialize_with = "regex_deser")]
+    pub alert: Option<Regex>,
+    pub host_uuid: Option<String>,
+    #[serde(default, deserialize_with = "regex_deser")]
+    pub hostname: Option<Regex>,
+    pub severity: Option<String>,
+    pub cid: Option<String>,
+
+    /// Mailboxes receiving the mails for this Route
+    #[serde(default, deserialize_with = "mailboxes_deser")]
+    pub receivers: Vec<Mailbox>,
+    /// Name of the on-call schedule whose current member also receives the mails
+    pub oncall: Option<String>,
+    /// Channels (smtp, slack, ...) used for this Route, all of them if not set
+    pub channels: Option<Vec<String>>,
+    /// Keep evaluating the next Routes even if this one matched
+    #[serde(default, rename = "continue")]
+    pub continue_matching: bool,
+    /// Name of the escalation policy of the incidents of this Route
+    pub escalation_policy: Option<String>,
+    /// When the incidents of this Route can be delivered, at any time if empty
+    #[serde(default)]
+    pub delivery_windows: Vec<DeliveryWindow>,
+}
+
+impl Route {
+    fn matches(&self, alert: &Alerts, incident: &Incidents) -> bool {
+        self.alert
+            .as_ref()
+            .is_none_or(|re| re.is_match(&alert.name))
+            && self
+                .host_uuid
+                .as_ref()
+                .is_none_or(|uuid| uuid == &incident.host_uuid)
+            && self
+                .hostname
+                .as_ref()
+                .is_none_or(|re| re.is_match(&incident.hostname))
+            && self.severity.as_ref().is_none_or(|severity| {
+                severity.eq_ignore_ascii_case(&Severity::from(incident.severity).to_string())
+            })
+            && self
+                .cid
+                .as_ref()
+                .is_none_or(|cid| cid == &incident.cid.to_string())
+    }
+}
+
+/// Where an Event should be delivered, the result of the routing
+#[derive(Debug, Default, Clone)]
+pub struct Destination {
+    /// Mailboxes receiving the mails
+    pub receivers: Vec<Mailbox>,
+    /// Channels allowed to deliver the Event, all of them if None
+    pub channels: Option<Vec<String>>,
+    /// Sender of the mails, smtp_email_sender if None
+    pub sender: Option<Mailbox>,
+    /// Time range during which only Critical incidents are sent
+    pub quiet_hours: Option<QuietHours>,
+    /// Escalation policy notifying more people while the incident is not acknowledged
+    pub escalation_policy: Option<String>,
+    /// When the incidents can be delivered, at any time if empty
+    pub delivery_windows: Vec<DeliveryWindow>,
+}
+
+impl Destination {
+    /// Check if the channel (Notifier::name) is allowed to deliver the Event
+    pub fn allows(&self, channel: &str) -> bool {
+        self.channels
+            .as_ref()
+            .is_none_or(|channels| channels.iter().any(|c| c.eq_ignore_ascii_case(channel)))
+    }
+
+    /// Date (UTC) until which the incident must be held because of the quiet hours
+    /// or the delivery windows, None if it can be sent now
+    pub fn held_until(&self, incident: &Incidents) -> Option<NaiveDateTime> {
+        let severity = Severity::from(incident.severity);
+        let quiet = match severity {
+            Severity::Warning => self
+                .quiet_hours
+                .as_ref()
+                .filter(|quiet_hours| quiet_hours.is_active())
+                .map(QuietHours::ends_at),
+            Severity::Critical => None,
+        };
+
+        quiet.max(delivery::held_until(&self.delivery_windows, severity))
+    }
+
+    fn merge(&mut self, route: &Route, channels: Option<Vec<String>>) {
+        for receiver in &oncall::with_on_call(&route.receivers, route.oncall.as_ref()) {
+            if !self.receivers.contains(receiver) {
+                self.receivers.push(receiver.to_owned());
+            }
+        }
+
+        self.channels = match (self.channels.take(), channels) {
+            (Some(mut channels), Some(other)) => {
+                channels.extend(other);
+                Some(channels)
+            }
+            _ => None,
+        };
+
+        // The first policy (and windows) found wins, as for the sender
+        if self.escalation_policy.is_none() {
+            self.escalation_policy = route.escalation_policy.clone();
+        }
+        if self.delivery_windows.is_empty() {
+            self.delivery_windows = route.delivery_windows.clone();
+        }
+    }
+}
+
+/// Resolve the Destination of an incident using the Routes of the Config.
+///
+/// Routes are evaluated in order and the first matching one is used (unless it
+/// has `continue`), if none matches the default route (smtp_email_receiver, every channel) is used.
+///
+/// If the tenant (cid) of the incident has a profile, only the Routes scoped to its cid
+/// are evaluated and its profile replaces the default route, so that the incidents of a
+/// tenant never reach the receivers of another one. Its Routes without channels use the
+/// ones of its profile, never every channel.
+pub fn resolve(config: &Config, alert: &Alerts, incident: &Incidents) -> Destination {
+    let cid = incident.cid.to_string();
+    let profile = config.tenants.iter().find(|t| t.cid == cid);
+    let channels_of = |route: &Route| match profile {
+        Some(profile) => Some(
+            route
+                .channels
+                .clone()
+                .unwrap_or_else(|| profile.channels.clone()),
+        ),
+        None => route.channels.clone(),
+    };
+
+    let mut destination: Option<Destination> = None;
+    for route in config
+        .routes
+        .iter()
+        .filter(|r| profile.is_none() || r.cid.as_ref() == Some(&cid))
+        .filter(|r| r.matches(alert, incident))
+    {
+        match destination.as_mut() {
+            Some(dest) => dest.merge(route, channels_of(route)),
+            None => {
+                destination = Some(Destination {
+                    receivers: oncall::with_on_call(&route.receivers, route.oncall.as_ref()),
+                    channels: channels_of(route),
+                    escalation_policy: route.escalation_policy.clone(),
+                    delivery_windows: route.delivery_windows.clone(),
+                    ..Default::default()
+                })
+            }
+        }
+
+        if !route.continue_matching {
+            break;
+        }
+    }
+
+    match profile {
+        Some(profile) => {
+            let mut destination = destination.unwrap_or_else(|| Destination {
+                receivers: profile.receivers.clone(),
+                channels: Some(profile.channels.clone()),
+                ..Default::default()
+            });
+            destination.sender = profile.sender.clone();
+            destination.quiet_hours = profile.quiet_hours.clone();
+            if destination.delivery_windows.is_empty() {
+                destination.delivery_windows = profile.delivery_windows.clone();
+            }
+            destination
+        }
+        None => {
+            let mut destination = destination.unwrap_or_else(|| Destination {
+                receivers: vec![config.smtp_email_receiver.clone()],
+                ..Default::default()
+            });
+            if destination.delivery_windows.is_empty() {
+                destination.delivery_windows = config.delivery_windows.clone();
+            }
+            destination
+        }
+    }
+}
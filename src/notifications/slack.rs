@@ -0,0 +1,231 @@
+use diesel::prelude::*;
+use serde::Deserialize;
+use simd_json::{json, prelude::ValueAsMutObject, OwnedValue};
+use sproot::ConnType;
+
+use super::{Event, EventKind, Notifier, NotifierError, AGENT};
+use crate::{schema::incidents_slack_threads, utils::config::Config};
+
+const SLACK_POST_MESSAGE: &str = "https://slack.com/api/chat.postMessage";
+
+/// Where the Slack messages are sent.
+///
+/// Incoming webhooks don't return the ts of the message, so threading
+/// the replies is only possible using the Web API with a bot token.
+enum SlackTarget {
+    Webhook(String),
+    Api { token: String, channel: String },
+}
+
+/// Structure representing the response of chat.postMessage
+#[derive(Deserialize)]
+struct PostMessageResponse {
+    ok: bool,
+    ts: Option<String>,
+    error: Option<String>,
+}
+
+/// Structure representing the ts (Slack message id) of the message sent for an incident,
+/// used to reply to the original message in its thread.
+#[derive(Insertable, Debug)]
+#[diesel(table_name = incidents_slack_threads)]
+struct SlackThread {
+    incident_id: i32,
+    ts: String,
+}
+
+/// Get the ts of the message sent for the incident, if any
+fn find_thread(conn: &mut ConnType, incident_id: i32) -> Option<String> {
+    incidents_slack_threads::table
+        .find(incident_id)
+        .select(incidents_slack_threads::ts)
+        .first(conn)
+        .optional()
+        .unwrap_or_else(|err| {
+            error!(
+                "Slack: could not get the thread of the incident {}: {}",
+                incident_id, err
+            );
+            None
+        })
+}
+
+/// Save the ts of the message sent for the incident, replacing the previous one
+fn save_thread(conn: &mut ConnType, incident_id: i32, ts: String) {
+    let res = diesel::insert_into(incidents_slack_threads::table)
+        .values(&SlackThread { incident_id, ts })
+        .on_conflict(incidents_slack_threads::incident_id)
+        .do_update()
+        .set(incidents_slack_threads::ts.eq(diesel::upsert::excluded(incidents_slack_threads::ts)))
+        .execute(conn);
+    if let Err(err) = res {
+        error!(
+            "Slack: could not save the thread of the incident {}: {}",
+            incident_id, err
+        );
+    }
+}
+
+/// Forget the thread of the incident
+fn delete_thread(conn: &mut ConnType, incident_id: i32) {
+    if let Err(err) = diesel::delete(incidents_slack_threads::table.find(incident_id)).execute(conn)
+    {
+        error!(
+            "Slack: could not delete the thread of the incident {}: {}",
+            incident_id, err
+        );
+    }
+}
+
+/// Notifier sending the incidents as Block Kit messages to Slack
+pub struct SlackNotifier {
+    target: SlackTarget,
+}
+
+impl SlackNotifier {
+    /// Build the SlackNotifier if Slack is configured, preferring the Web API over the webhook.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let target = match (
+            &config.slack_bot_token,
+            &config.slack_channel,
+            &config.slack_webhook_url,
+        ) {
+            (Some(token), Some(channel), _) => SlackTarget::Api {
+                token: token.to_owned(),
+                channel: channel.to_owned(),
+            },
+            (_, _, Some(url)) => SlackTarget::Webhook(url.to_owned()),
+            _ => return None,
+        };
+
+        Some(Self { target })
+    }
+
+    /// Build the message, using an attachment to get the severity color bar.
+    fn build_message(&self, event: &Event, thread_ts: Option<String>) -> OwnedValue {
+        let fields: Vec<OwnedValue> = event
+            .fields()
+            .into_iter()
+            .map(|(label, value)| {
+                json!({
+                    "type": "mrkdwn",
+                    "text": format!("*{}*\n`{}`", label, value),
+                })
+            })
+            .collect();
+
+        let mut message = json!({
+            "text": format!("{}: {}", event.title(), event.incident.hostname),
+            "attachments": [{
+                "color": event.color(),
+                "blocks": [
+                    {
+                        "type": "header",
+                        "text": {
+                            "type": "plain_text",
+                            "text": format!("{} - {}", event.title(), event.incident.hostname),
+                        },
+                    },
+                    {
+                        "type": "section",
+                        "text": {
+                            "type": "mrkdwn",
+                            "text": event.summary(),
+                        },
+                    },
+                    {
+                        "type": "section",
+                        "fields": fields,
+                    },
+                ],
+            }],
+        });
+
+        if let Some(obj) = message.as_object_mut() {
+            if let SlackTarget::Api { channel, .. } = &self.target {
+                obj.insert("channel".into(), channel.to_owned().into());
+            }
+            if let Some(ts) = thread_ts {
+                obj.insert("thread_ts".into(), ts.into());
+            }
+        }
+
+        message
+    }
+
+    /// Post the payload to Slack, returning the ts of the message if using the Web API.
+    fn post(&self, payload: &str) -> Result<Option<String>, NotifierError> {
+        match &self.target {
+            SlackTarget::Webhook(url) => {
+                AGENT
+                    .post(url)
+                    .set("Content-Type", "application/json")
+                    .send_string(payload)
+                    .map_err(|err| NotifierError(err.to_string()))?;
+                Ok(None)
+            }
+            SlackTarget::Api { token, .. } => {
+                let mut body = AGENT
+                    .post(SLACK_POST_MESSAGE)
+                    .set("Content-Type", "application/json; charset=utf-8")
+                    .set("Authorization", &format!("Bearer {}", token))
+                    .send_string(payload)
+                    .map_err(|err| NotifierError(err.to_string()))?
+                    .into_string()
+                    .map_err(|err| NotifierError(err.to_string()))?
+                    .into_bytes();
+
+                // Slack answers 200 even on error, the status is in the body
+                let resp: PostMessageResponse = simd_json::from_slice(&mut body)
+                    .map_err(|err| NotifierError(format!("invalid response: {}", err)))?;
+                if !resp.ok {
+                    return Err(NotifierError(resp.error.unwrap_or_default()));
+                }
+                Ok(resp.ts)
+            }
+        }
+    }
+}
+
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &'static str {
+        "Slack"
+    }
+
+    fn notify(&self, conn: &mut ConnType, event: &Event) -> Result<(), NotifierError> {
+        let incident_id = event.incident.id;
+        // Only the follow-ups (escalated/resolved) are replied in the thread,
+        // which is stored in the database to survive the restarts.
+        let thread_ts = match event.kind {
+            EventKind::New => None,
+            _ => find_thread(conn, incident_id),
+        };
+        let payload = simd_json::to_string(&self.build_message(event, thread_ts))
+            .map_err(|err| NotifierError(format!("could not serialize the payload: {}", err)))?;
+
+        let ts = self.post(&payload)?;
+        if let (EventKind::New, Some(ts)) = (event.kind, ts) {
+            save_thread(conn, incident_id, ts);
+        }
+
+        // The thread is not needed anymore once the incident is resolved
+        if event.kind == EventKind::Resolved {
+            delete_thread(conn, incident_id);
+        }
+
+        Ok(())
+    }
+
+    fn notify_summary(&self, text: &str) -> Result<(), NotifierError> {
+        let mut message = json!({ "text": text });
+        if let (Some(obj), SlackTarget::Api { channel, .. }) =
+            (message.as_object_mut(), &self.target)
+        {
+            obj.insert("channel".into(), channel.to_owned().into());
+        }
+        let payload = simd_json::to_string(&message)
+            .map_err(|err| NotifierError(format!("could not serialize the payload: {}", err)))?;
+
+        self.post(&payload).map(|_| ())
+    }
+}
@@ -0,0 +1,178 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::client::{Tls, TlsParameters};
+use lettre::transport::smtp::PoolConfig;
+use lettre::{Message, SmtpTransport, Transport};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+use super::NotifierError;
+use crate::{
+    utils::{
+        config::{default_smtp_port, default_smtp_tls},
+        spawn_periodic,
+    },
+    CONFIG,
+};
+
+// Lazy static of the SMTP relays used to send mails, in the order they're tried.
+// Each is built using rustls and a pool of 16 items.
+static RELAYS: Lazy<Vec<Relay>> = Lazy::new(|| {
+    if CONFIG.smtp_host.is_empty() {
+        error!("MAILER: smtp_host is required by the smtp mail_transport");
+        std::process::exit(1);
+    }
+    let primary = SmtpRelay {
+        host: CONFIG.smtp_host.to_owned(),
+        port: CONFIG.smtp_port,
+        tls: CONFIG.smtp_tls,
+        user: CONFIG.smtp_user.to_owned(),
+        password: CONFIG.smtp_password.to_owned(),
+    };
+
+    std::iter::once(&primary)
+        .chain(CONFIG.smtp_relays.iter())
+        .map(|settings| match Relay::new(settings) {
+            Ok(relay) => relay,
+            Err(e) => {
+                error!(
+                    "MAILER: cannot get the smtp_transport of {}: {}",
+                    settings.host, e
+                );
+                std::process::exit(1);
+            }
+        })
+        .collect()
+});
+
+/// Structure representing a fallback SMTP relay, with its own credentials
+#[derive(Debug, Deserialize, Clone)]
+pub struct SmtpRelay {
+    pub host: String,
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    #[serde(default = "default_smtp_tls")]
+    pub tls: bool,
+    pub user: String,
+    pub password: String,
+}
+
+/// Structure representing an SMTP relay and whether it was reachable lately
+struct Relay {
+    host: String,
+    transport: SmtpTransport,
+    healthy: AtomicBool,
+}
+
+impl Relay {
+    fn new(settings: &SmtpRelay) -> Result<Self, lettre::transport::smtp::Error> {
+        let creds = Credentials::new(settings.user.to_owned(), settings.password.to_owned());
+
+        let transport = if settings.tls {
+            SmtpTransport::builder_dangerous(&settings.host)
+                .tls(Tls::Required(TlsParameters::new(settings.host.to_owned())?))
+        } else {
+            SmtpTransport::builder_dangerous(&settings.host)
+        };
+
+        Ok(Self {
+            host: settings.host.to_owned(),
+            transport: transport
+                .port(settings.port)
+                .credentials(creds)
+                .pool_config(PoolConfig::new().max_size(16))
+                .build(),
+            // Every relay is assumed to be up until proven otherwise
+            healthy: AtomicBool::new(true),
+        })
+    }
+
+    /// Update the health of the relay, logging when it changes
+    fn set_healthy(&self, healthy: bool) {
+        if self.healthy.swap(healthy, Ordering::Relaxed) != healthy {
+            if healthy {
+                info!("MAILER: relay {} is back up", self.host);
+            } else {
+                warn!("MAILER: relay {} is marked as down", self.host);
+            }
+        }
+    }
+}
+
+/// Check the connection to each relay and update their health.
+///
+/// A relay being down is not fatal, the mails are sent through the other ones
+/// until it's back up.
+pub fn check_relays() {
+    for relay in RELAYS.iter() {
+        match relay.transport.test_connection() {
+            Ok(true) => relay.set_healthy(true),
+            Ok(false) => {
+                warn!("MAILER: relay {} refused the connection", relay.host);
+                relay.set_healthy(false);
+            }
+            Err(e) => {
+                warn!("MAILER: test of the relay {} failed: {}", relay.host, e);
+                relay.set_healthy(false);
+            }
+        }
+    }
+
+    if RELAYS
+        .iter()
+        .all(|relay| !relay.healthy.load(Ordering::Relaxed))
+    {
+        error!("MAILER: no SMTP relay is reachable, the mails will be retried from the outbox");
+    }
+}
+
+/// Send the email through the first relay accepting it, the healthy ones being tried first.
+pub fn send(email: &Message) -> Result<(), NotifierError> {
+    // The relays down are still tried last, in case they're back before the health check
+    let (healthy, down): (Vec<&Relay>, Vec<&Relay>) = RELAYS
+        .iter()
+        .partition(|relay| relay.healthy.load(Ordering::Relaxed));
+
+    let mut failed = Vec::new();
+    for relay in healthy.into_iter().chain(down) {
+        match relay.transport.send(email) {
+            Ok(_) => {
+                relay.set_healthy(true);
+                return Ok(());
+            }
+            // The mail itself was rejected (invalid address, ...), another relay would do the same
+            Err(err) if err.is_permanent() => {
+                return Err(NotifierError(format!(
+                    "could not send email through {}: {}",
+                    relay.host, err
+                )));
+            }
+            Err(err) => {
+                warn!(
+                    "MAILER: could not send email through {}: {}",
+                    relay.host, err
+                );
+                relay.set_healthy(false);
+                failed.push(format!("{}: {}", relay.host, err));
+            }
+        }
+    }
+
+    Err(NotifierError(format!(
+        "could not send email through any relay: {}",
+        failed.join(", ")
+    )))
+}
+
+/// Start checking the health of the relays every smtp_health_interval seconds
+pub fn start_worker() {
+    spawn_periodic(
+        "SMTP",
+        Duration::from_secs(CONFIG.smtp_health_interval),
+        check_relays,
+    );
+}
@@ -0,0 +1,268 @@
+use std::io::Write;
+
+use diesel::{
+    sql_query,
+    sql_types::{Float8, Nullable, Text},
+    QueryableByName, RunQueryDsl,
+};
+use flate2::{write::ZlibEncoder, Compression};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sproot::{models::Alerts, ConnType};
+
+use crate::CONFIG;
+
+/// Content-ID of the sparkline attached to the mails, referenced as "cid:sparkline"
+pub const SPARKLINE_CID: &str = "sparkline";
+
+const WIDTH: usize = 480;
+const HEIGHT: usize = 120;
+const PADDING: usize = 8;
+
+const BACKGROUND: [u8; 3] = [0x30, 0x30, 0x30];
+const LINE: [u8; 3] = [0x3b, 0x82, 0xf6];
+const WARN: [u8; 3] = [0xe7, 0xd7, 0x56];
+const CRIT: [u8; 3] = [0xd9, 0x6f, 0x6f];
+
+// Extract the threshold from the warn/crit expressions, like "$this > 80"
+static THRESHOLD: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\$this\s*(?:>=|<=|==|!=|>|<)\s*(-?[0-9]+(?:\.[0-9]+)?)").unwrap());
+
+// Parse the lookup of the alerts, like "avg pct 10m of w,x over y,z" or "max abs 5m of x"
+static LOOKUP: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^\s*(avg|sum|min|max)\s+(pct|abs)\s+\S+\s+of\s+([\w,\s]+?)(?:\s+over\s+([\w,\s]+?))?\s*$",
+    )
+    .unwrap()
+});
+
+// Table and column names allowed in the query of the points
+static IDENT: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").unwrap());
+
+/// Structure representing a value of the lookup, NULL if the divisor of a pct was 0
+#[derive(QueryableByName)]
+struct Point {
+    #[diesel(sql_type = Nullable<Float8>)]
+    value: Option<f64>,
+}
+
+/// Build the query of the last sparkline_points values of the lookup of the alert,
+/// None if its lookup can't be drawn.
+///
+/// The lookup is aggregated over each interval (timing) of the alert instead of over
+/// its timeframe, e.g. "avg pct 10m of w over y" gives an avg(w)/avg(y) every timing seconds.
+fn points_query(alert: &Alerts) -> Option<String> {
+    let caps = LOOKUP.captures(&alert.lookup)?;
+    let aggr = &caps[1];
+    let sum_of = |cols: &str| -> Option<String> {
+        cols.split(',')
+            .map(str::trim)
+            .map(|col| IDENT.is_match(col).then(|| format!("{}::float8", col)))
+            .collect::<Option<Vec<_>>>()
+            .map(|cols| cols.join(" + "))
+    };
+
+    let of = sum_of(&caps[3])?;
+    let value = match (&caps[2], caps.get(4)) {
+        ("pct", Some(over)) => format!(
+            "100 * {aggr}({}) / NULLIF({aggr}({}), 0)",
+            of,
+            sum_of(over.as_str())?,
+            aggr = aggr
+        ),
+        ("abs", None) => format!("{}({})", aggr, of),
+        _ => return None,
+    };
+    if !IDENT.is_match(&alert.table) {
+        return None;
+    }
+
+    let timing = alert.timing.max(1);
+    let filter = alert
+        .where_clause
+        .as_ref()
+        .map(|clause| format!(" AND {}", clause))
+        .unwrap_or_default();
+    Some(format!(
+        "SELECT value FROM (\
+            SELECT floor(extract(epoch FROM created_at) / {timing}) AS bucket, {value} AS value \
+            FROM {table} \
+            WHERE host_uuid = $1 AND created_at > now() at time zone 'utc' - INTERVAL '{span} seconds'{filter} \
+            GROUP BY bucket ORDER BY bucket DESC LIMIT {points}\
+        ) AS points ORDER BY bucket",
+        timing = timing,
+        value = value,
+        table = alert.table,
+        span = i64::from(timing) * CONFIG.sparkline_points as i64,
+        filter = filter,
+        points = CONFIG.sparkline_points,
+    ))
+}
+
+/// Render the PNG sparkline of the last values of the lookup of the alert, queried
+/// from its table, if there's enough of them
+pub fn render(conn: &mut ConnType, alert: &Alerts) -> Option<Vec<u8>> {
+    if CONFIG.sparkline_points == 0 {
+        return None;
+    }
+    let query = points_query(alert)?;
+
+    let points: Vec<f64> = match sql_query(&query)
+        .bind::<Text, _>(&alert.host_uuid)
+        .load::<Point>(conn)
+    {
+        Ok(points) => points
+            .into_iter()
+            .filter_map(|point| point.value)
+            .filter(|value| value.is_finite())
+            .collect(),
+        Err(err) => {
+            error!(
+                "[{}] Sparkline: cannot query the points of alert {}: {}",
+                alert.id, alert.name, err
+            );
+            return None;
+        }
+    };
+
+    render_points(&points, threshold(&alert.warn), threshold(&alert.crit))
+}
+
+/// Render the PNG sparkline of the points, with the thresholds drawn as dashed lines
+pub fn render_points(points: &[f64], warn: Option<f64>, crit: Option<f64>) -> Option<Vec<u8>> {
+    if points.len() < 2 {
+        return None;
+    }
+
+    // The thresholds are part of the range so that the distance to them is visible
+    let (mut min, mut max) = points
+        .iter()
+        .chain(warn.iter())
+        .chain(crit.iter())
+        .fold((f64::MAX, f64::MIN), |(min, max), v| {
+            (min.min(*v), max.max(*v))
+        });
+    if max <= min {
+        min -= 1.0;
+        max += 1.0;
+    }
+
+    let y_of = |value: f64| -> i64 {
+        let ratio = (value - min) / (max - min);
+        (PADDING as f64 + (1.0 - ratio) * (HEIGHT - 2 * PADDING) as f64).round() as i64
+    };
+    let x_of = |idx: usize| -> i64 {
+        (PADDING as f64 + idx as f64 * (WIDTH - 2 * PADDING) as f64 / (points.len() - 1) as f64)
+            .round() as i64
+    };
+
+    let mut canvas = Canvas::new(BACKGROUND);
+    for (threshold, color) in [(warn, WARN), (crit, CRIT)] {
+        if let Some(threshold) = threshold {
+            let y = y_of(threshold);
+            canvas.line((0, y), (WIDTH as i64 - 1, y), color, true);
+        }
+    }
+    for (idx, pair) in points.windows(2).enumerate() {
+        canvas.line(
+            (x_of(idx), y_of(pair[0])),
+            (x_of(idx + 1), y_of(pair[1])),
+            LINE,
+            false,
+        );
+    }
+
+    canvas.to_png()
+}
+
+/// Get the numeric threshold of a warn/crit expression, if it has a simple one
+fn threshold(expr: &str) -> Option<f64> {
+    THRESHOLD
+        .captures(expr)
+        .and_then(|caps| caps.get(1))
+        .and_then(|m| m.as_str().parse().ok())
+}
+
+/// Structure representing an RGB image being drawn
+struct Canvas {
+    pixels: Vec<[u8; 3]>,
+}
+
+impl Canvas {
+    fn new(background: [u8; 3]) -> Self {
+        Self {
+            pixels: vec![background; WIDTH * HEIGHT],
+        }
+    }
+
+    /// Draw a 2px wide line using Bresenham's algorithm
+    fn line(&mut self, from: (i64, i64), to: (i64, i64), color: [u8; 3], dashed: bool) {
+        let (mut x, mut y) = from;
+        let (dx, dy) = ((to.0 - x).abs(), -(to.1 - y).abs());
+        let (sx, sy) = (if x < to.0 { 1 } else { -1 }, if y < to.1 { 1 } else { -1 });
+        let mut err = dx + dy;
+        let mut step = 0;
+
+        loop {
+            if !dashed || (step / 6) % 2 == 0 {
+                self.plot(x, y, color);
+                self.plot(x, y + 1, color);
+            }
+            if (x, y) == to {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+            step += 1;
+        }
+    }
+
+    fn plot(&mut self, x: i64, y: i64, color: [u8; 3]) {
+        if (0..WIDTH as i64).contains(&x) && (0..HEIGHT as i64).contains(&y) {
+            self.pixels[y as usize * WIDTH + x as usize] = color;
+        }
+    }
+
+    /// Encode the canvas as an 8 bits RGB PNG
+    fn to_png(&self) -> Option<Vec<u8>> {
+        // Each row starts with its filter type (0 = None)
+        let mut raw = Vec::with_capacity(HEIGHT * (1 + WIDTH * 3));
+        for row in self.pixels.chunks(WIDTH) {
+            raw.push(0);
+            raw.extend(row.iter().flatten());
+        }
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&raw).ok()?;
+        let data = encoder.finish().ok()?;
+
+        let mut header = Vec::with_capacity(13);
+        header.extend((WIDTH as u32).to_be_bytes());
+        header.extend((HEIGHT as u32).to_be_bytes());
+        // Bit depth 8, color type RGB, default compression, filter and no interlace
+        header.extend([8, 2, 0, 0, 0]);
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        write_chunk(&mut png, b"IHDR", &header);
+        write_chunk(&mut png, b"IDAT", &data);
+        write_chunk(&mut png, b"IEND", &[]);
+        Some(png)
+    }
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    png.extend(kind);
+    png.extend(data);
+
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(kind);
+    hasher.update(data);
+    png.extend(hasher.finalize().to_be_bytes());
+}
@@ -0,0 +1,219 @@
+use std::{fmt, fs, path::Path};
+
+use minijinja::{Environment, UndefinedBehavior};
+use once_cell::sync::OnceCell;
+use simd_json::OwnedValue;
+
+use super::{digest, mail, EventKind};
+use crate::CONFIG;
+
+/// Names of the templates which can be overridden, as expected in the templates_dir
+pub const TEMPLATE_NAMES: [&str; 10] = [
+    "incident.html",
+    "incident.txt",
+    "escalate.html",
+    "escalate.txt",
+    "resolved.html",
+    "resolved.txt",
+    "reminder.html",
+    "reminder.txt",
+    "digest.html",
+    "digest.txt",
+];
+
+// Templates loaded from the templates_dir
+static OVERRIDES: OnceCell<Environment<'static>> = OnceCell::new();
+
+#[derive(Debug)]
+pub struct TemplateError(pub String);
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<minijinja::Error> for TemplateError {
+    fn from(err: minijinja::Error) -> Self {
+        Self(err.to_string())
+    }
+}
+
+/// Build the environment in which the templates are loaded.
+///
+/// The templates use the Jinja syntax, an unknown variable being an error when
+/// printed but false in an if. The values are html escaped in the html templates.
+fn environment() -> Environment<'static> {
+    let mut env = Environment::new();
+    env.set_undefined_behavior(UndefinedBehavior::SemiStrict);
+    env
+}
+
+/// Load the templates from the templates_dir, if any, and check them against sample data.
+///
+/// Must be called once at startup, before any template is rendered.
+pub fn init() -> Result<(), TemplateError> {
+    let mut env = environment();
+    let mut names = Vec::new();
+
+    if let Some(dir) = &CONFIG.templates_dir {
+        for name in TEMPLATE_NAMES {
+            let path = Path::new(dir).join(name);
+            if !path.exists() {
+                continue;
+            }
+
+            let source = fs::read_to_string(&path)
+                .map_err(|err| TemplateError(format!("cannot read {}: {}", path.display(), err)))?;
+            env.add_template_owned(name, source)
+                .map_err(|err| TemplateError(format!("{}: {}", name, err)))?;
+            info!(
+                "Templates: using {} instead of the built-in one",
+                path.display()
+            );
+            names.push(name);
+        }
+    }
+
+    // Can only fail if init was already called, in which case the templates are the same
+    let _ = OVERRIDES.set(env);
+
+    // Render each loaded template to catch the unknown variables now instead of at the first incident
+    for name in names {
+        render_sample(name).map_err(|err| TemplateError(format!("{}: {}", name, err)))?;
+    }
+    Ok(())
+}
+
+/// Render the template loaded from the templates_dir, if there's one with this name.
+pub fn render(name: &str, ctx: &OwnedValue) -> Option<Result<String, TemplateError>> {
+    OVERRIDES
+        .get()
+        .and_then(|env| env.get_template(name).ok())
+        .map(|template| Ok(template.render(ctx)?))
+}
+
+/// Render the template (loaded or built-in) against sample data.
+pub fn render_sample(name: &str) -> Result<String, TemplateError> {
+    let (base, ext) = name.split_once('.').unwrap_or((name, ""));
+    let content = match base {
+        "incident" => mail::render_sample(EventKind::New)?,
+        "escalate" => mail::render_sample(EventKind::Escalated)?,
+        "resolved" => mail::render_sample(EventKind::Resolved)?,
+        "reminder" => mail::render_sample(EventKind::Reminder)?,
+        "digest" => digest::render_sample()?,
+        _ => return Err(unknown_template(name)),
+    };
+
+    match ext {
+        "html" => Ok(content.html.unwrap_or_default()),
+        "txt" => Ok(content.text),
+        _ => Err(unknown_template(name)),
+    }
+}
+
+fn unknown_template(name: &str) -> TemplateError {
+    TemplateError(format!(
+        "unknown template {}, expected one of {}",
+        name,
+        TEMPLATE_NAMES.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use simd_json::{json, OwnedValue};
+
+    use super::{environment, TemplateError};
+
+    fn render(name: &str, source: &str, ctx: &OwnedValue) -> Result<String, TemplateError> {
+        let mut env = environment();
+        env.add_template_owned(name.to_owned(), source.to_owned())?;
+        Ok(env.get_template(name)?.render(ctx)?)
+    }
+
+    #[test]
+    fn if_else() {
+        let source = "{% if ack_url %}<a href=\"{{ ack_url }}\">ack</a>{% else %}no ack{% endif %}\
+                      {% for item in items %}{% if item.critical %}!{% endif %}{{ item.name }}{% endfor %}";
+
+        let ctx = json!({
+            "ack_url": "https://ack?a=1&b=2",
+            "items": [{"name": "disk", "critical": true}, {"name": "cpu", "critical": false}],
+        });
+        assert_eq!(
+            render("incident.html", source, &ctx).unwrap(),
+            "<a href=\"https:&#x2f;&#x2f;ack?a=1&amp;b=2\">ack</a>!diskcpu"
+        );
+
+        let ctx = json!({ "ack_url": "", "items": [] });
+        assert_eq!(render("incident.html", source, &ctx).unwrap(), "no ack");
+
+        // An unknown variable is false in an if
+        let ctx = json!({ "items": [] });
+        assert_eq!(render("incident.html", source, &ctx).unwrap(), "no ack");
+    }
+
+    #[test]
+    fn unknown_variables() {
+        let ctx = json!({ "item": {"name": "disk"} });
+        assert!(render("incident.txt", "{{ missing }}", &ctx).is_err());
+        assert!(render("incident.txt", "{{ item.missing }}", &ctx).is_err());
+        assert!(render("incident.txt", "{% for a in missing %}{% endfor %}", &ctx).is_err());
+    }
+
+    #[test]
+    fn lists() {
+        let ctx = json!({ "hosts": ["web-1", "web-2"] });
+        assert_eq!(
+            render("incident.txt", "{{ hosts }}", &ctx).unwrap(),
+            "[\"web-1\", \"web-2\"]"
+        );
+        assert_eq!(
+            render("incident.txt", "{{ hosts | join(\", \") }}", &ctx).unwrap(),
+            "web-1, web-2"
+        );
+    }
+
+    #[test]
+    fn loop_scoping() {
+        // The loop variable shadows the field of the same name, and is gone after the loop
+        let ctx = json!({ "name": "outer", "items": [{"name": "a"}, {"name": "b"}] });
+        let source = "{% for name in items %}{{ name.name }}{% endfor %}{{ name }}";
+        assert_eq!(render("digest.txt", source, &ctx).unwrap(), "abouter");
+
+        let source = "{% for item in items %}{% endfor %}{{ item }}";
+        assert!(render("digest.txt", source, &ctx).is_err());
+    }
+
+    #[test]
+    fn escaping() {
+        let ctx = json!({ "name": "cpu & <load>" });
+        assert_eq!(
+            render("incident.html", "<b>{{ name }}</b>", &ctx).unwrap(),
+            "<b>cpu &amp; &lt;load&gt;</b>"
+        );
+        assert_eq!(
+            render("incident.txt", "{{ name }}", &ctx).unwrap(),
+            "cpu & <load>"
+        );
+    }
+
+    #[test]
+    fn unbalanced_blocks() {
+        for source in [
+            "{% if a %}",
+            "{% endif %}",
+            "{% else %}",
+            "{% if a %}{% else %}{% else %}{% endif %}",
+            "{% for a in b %}{% endif %}",
+            "{% if a %}{% endfor %}",
+        ] {
+            assert!(
+                render("incident.txt", source, &json!({})).is_err(),
+                "{}",
+                source
+            );
+        }
+    }
+}
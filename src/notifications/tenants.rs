@@ -0,0 +1,158 @@
+use chrono::{prelude::Utc, NaiveDateTime, NaiveTime, TimeZone};
+use chrono_tz::Tz;
+use lettre::message::Mailbox;
+use serde::Deserialize;
+
+use super::delivery::DeliveryWindow;
+use crate::{
+    utils::config::{mailboxes_deser, opt_mailbox_deser, opt_tz_deser, time_deser},
+    CONFIG,
+};
+
+/// Structure representing the notification profile of a tenant (cid), as defined in the Config.
+///
+/// Incidents of a tenant having a profile are only routed using the Routes
+/// scoped to its cid and its profile, never using the global ones.
+#[derive(Debug, Deserialize, Clone)]
+pub struct TenantProfile {
+    pub cid: String,
+
+    /// Mailboxes receiving the mails if no Route of the tenant matched
+    #[serde(default, deserialize_with = "mailboxes_deser")]
+    pub receivers: Vec<Mailbox>,
+    /// Channels used if no Route of the tenant matched, only smtp by default
+    /// as the other channels are shared by every tenant.
+    #[serde(default = "default_channels")]
+    pub channels: Vec<String>,
+    /// Sender of the mails, instead of smtp_email_sender
+    #[serde(default, deserialize_with = "opt_mailbox_deser")]
+    pub sender: Option<Mailbox>,
+    pub quiet_hours: Option<QuietHours>,
+    /// Delivery windows used if no Route of the tenant has some
+    #[serde(default)]
+    pub delivery_windows: Vec<DeliveryWindow>,
+}
+
+fn default_channels() -> Vec<String> {
+    vec!["smtp".to_owned()]
+}
+
+/// Structure representing a time range during which only Critical incidents are sent,
+/// the Warning ones being held until its end.
+///
+/// As for the delivery windows, a range whose start and end are equal lasts 24 hours.
+#[derive(Debug, Deserialize, Clone)]
+pub struct QuietHours {
+    #[serde(deserialize_with = "time_deser")]
+    pub start: NaiveTime,
+    #[serde(deserialize_with = "time_deser")]
+    pub end: NaiveTime,
+    /// Timezone of the start and end, the timezone of the Config if not set
+    #[serde(default, deserialize_with = "opt_tz_deser")]
+    pub timezone: Option<Tz>,
+}
+
+impl QuietHours {
+    fn timezone(&self) -> Tz {
+        self.timezone.unwrap_or(CONFIG.timezone)
+    }
+
+    /// Check if we're currently in the quiet hours
+    pub fn is_active(&self) -> bool {
+        self.is_active_at(Utc::now().naive_utc())
+    }
+
+    /// Next (UTC) date at which the quiet hours end
+    pub fn ends_at(&self) -> NaiveDateTime {
+        self.end_after(Utc::now().naive_utc())
+    }
+
+    /// Check if the (UTC) date is in the quiet hours, handling the ranges over midnight
+    fn is_active_at(&self, now: NaiveDateTime) -> bool {
+        let now = Utc
+            .from_utc_datetime(&now)
+            .with_timezone(&self.timezone())
+            .time();
+        if self.start < self.end {
+            self.start <= now && now < self.end
+        } else {
+            now >= self.start || now < self.end
+        }
+    }
+
+    /// First (UTC) end of the quiet hours after the date
+    fn end_after(&self, now: NaiveDateTime) -> NaiveDateTime {
+        let tz = self.timezone();
+        let today = Utc.from_utc_datetime(&now).with_timezone(&tz).date_naive();
+
+        (0..=2)
+            .map(|days| today + chrono::Duration::days(days))
+            // An end skipped by a DST change is looked for the next day
+            .filter_map(|date| tz.from_local_datetime(&date.and_time(self.end)).earliest())
+            .map(|end| end.naive_utc())
+            .find(|end| *end > now)
+            .unwrap_or(now + chrono::Duration::days(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDateTime, NaiveTime};
+
+    use super::QuietHours;
+
+    fn quiet_hours(start: &str, end: &str) -> QuietHours {
+        QuietHours {
+            start: NaiveTime::parse_from_str(start, "%H:%M").unwrap(),
+            end: NaiveTime::parse_from_str(end, "%H:%M").unwrap(),
+            timezone: Some(chrono_tz::Europe::Paris),
+        }
+    }
+
+    fn utc(date: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn over_midnight() {
+        // 22:00 - 07:00 in Paris, which is UTC+2 in october
+        let night = quiet_hours("22:00", "07:00");
+        assert!(!night.is_active_at(utc("2026-10-16 19:59")));
+        assert!(night.is_active_at(utc("2026-10-16 20:00")));
+        assert!(night.is_active_at(utc("2026-10-17 04:59")));
+        assert!(!night.is_active_at(utc("2026-10-17 05:00")));
+
+        // Ends the next morning, or the same one after midnight
+        assert_eq!(
+            night.end_after(utc("2026-10-16 21:00")),
+            utc("2026-10-17 05:00")
+        );
+        assert_eq!(
+            night.end_after(utc("2026-10-16 23:00")),
+            utc("2026-10-17 05:00")
+        );
+    }
+
+    #[test]
+    fn start_equals_end() {
+        // As for the delivery windows, the range lasts 24 hours
+        let always = quiet_hours("08:00", "08:00");
+        assert!(always.is_active_at(utc("2026-10-17 05:59")));
+        assert!(always.is_active_at(utc("2026-10-17 06:00")));
+        assert_eq!(
+            always.end_after(utc("2026-10-17 06:00")),
+            utc("2026-10-18 06:00")
+        );
+    }
+
+    #[test]
+    fn dst_gap() {
+        // 02:30 does not exist in Paris on 2026-03-29 (02:00 -> 03:00), so they end the next day
+        let night = quiet_hours("23:00", "02:30");
+        assert!(night.is_active_at(utc("2026-03-28 23:30")));
+        assert_eq!(
+            night.end_after(utc("2026-03-28 23:30")),
+            utc("2026-03-30 00:30")
+        );
+    }
+}
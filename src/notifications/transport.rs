@@ -0,0 +1,98 @@
+use std::fs;
+
+use lettre::{
+    transport::{file::FileTransport, sendmail::SendmailTransport, stub::StubTransport},
+    Message, Transport,
+};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+use super::{smtp, NotifierError};
+use crate::CONFIG;
+
+// Lazy static of the transport used to send mails, as chosen by mail_transport
+static MAILER: Lazy<Mailer> = Lazy::new(|| match CONFIG.mail_transport {
+    MailTransport::Smtp => Mailer::Smtp,
+    MailTransport::Sendmail => Mailer::Sendmail(match &CONFIG.sendmail_command {
+        Some(command) => SendmailTransport::new_with_command(command),
+        None => SendmailTransport::new(),
+    }),
+    MailTransport::File => match &CONFIG.mail_spool_dir {
+        Some(dir) => Mailer::File(FileTransport::new(dir)),
+        None => {
+            error!("MAILER: mail_spool_dir is required by the file mail_transport");
+            std::process::exit(1);
+        }
+    },
+    MailTransport::Stub => Mailer::Stub(StubTransport::new_ok()),
+});
+
+/// Enum representing the transports which can be used to send mails
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MailTransport {
+    /// Through the SMTP relays (smtp_host and the smtp_relays)
+    #[default]
+    Smtp,
+    /// Through the local sendmail binary (sendmail_command)
+    Sendmail,
+    /// Written as one .eml file per mail in the mail_spool_dir
+    File,
+    /// Kept in memory and never sent, for the staging and tests
+    Stub,
+}
+
+enum Mailer {
+    Smtp,
+    Sendmail(SendmailTransport),
+    File(FileTransport),
+    Stub(StubTransport),
+}
+
+/// Check that the transport is usable and start what it needs.
+///
+/// Must be called once at startup, before any mail is sent.
+pub fn init() {
+    match &*MAILER {
+        Mailer::Smtp => {
+            // Check which SMTP relays are "ok", the ones down are only tried as a last resort
+            smtp::check_relays();
+            smtp::start_worker();
+        }
+        Mailer::Sendmail(_) => info!(
+            "MAILER: sending the mails using {}",
+            CONFIG.sendmail_command.as_deref().unwrap_or("sendmail")
+        ),
+        Mailer::File(_) => {
+            // SAFETY: the File transport can't be built without the mail_spool_dir
+            let dir = CONFIG.mail_spool_dir.as_ref().unwrap();
+            if let Err(err) = fs::create_dir_all(dir) {
+                error!("MAILER: cannot create the mail_spool_dir {}: {}", dir, err);
+                std::process::exit(1);
+            }
+            info!("MAILER: writing the mails to {}", dir);
+        }
+        Mailer::Stub(_) => warn!("MAILER: using the stub transport, no mail will be sent"),
+    }
+}
+
+/// Send the email using the configured transport
+pub fn send(email: &Message) -> Result<(), NotifierError> {
+    let res = match &*MAILER {
+        Mailer::Smtp => return smtp::send(email),
+        Mailer::Sendmail(transport) => transport.send(email).map_err(|err| err.to_string()),
+        Mailer::File(transport) => transport
+            .send(email)
+            .map(|_| ())
+            .map_err(|err| err.to_string()),
+        Mailer::Stub(transport) => {
+            debug!(
+                "MAILER: stub transport got a mail to {:?}",
+                email.envelope().to()
+            );
+            transport.send(email).map_err(|err| err.to_string())
+        }
+    };
+
+    res.map_err(|err| NotifierError(format!("could not send email: {}", err)))
+}
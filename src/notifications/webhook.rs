@@ -0,0 +1,97 @@
+use serde::Serialize;
+use sproot::{
+    models::{Alerts, Incidents},
+    ConnType,
+};
+
+use super::{Event, EventKind, Notifier, NotifierError, AGENT};
+use crate::{monitoring::Severity, utils::config::Config};
+
+/// Version of the JSON payload, bumped on every breaking change of its shape.
+///
+/// 2: the reminder kind and the summary payload were added.
+const WEBHOOK_VERSION: u8 = 2;
+
+/// Structure representing the JSON payload POSTed to the webhooks
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    version: u8,
+    kind: EventKind,
+    severity: String,
+    alert: &'a Alerts,
+    incident: &'a Incidents,
+}
+
+/// Structure representing the JSON payload POSTed for the summaries
+#[derive(Serialize)]
+struct SummaryPayload<'a> {
+    version: u8,
+    kind: &'static str,
+    text: &'a str,
+}
+
+/// Notifier POSTing the incidents as JSON to an url
+pub struct WebhookNotifier {
+    url: String,
+    name: &'static str,
+}
+
+impl WebhookNotifier {
+    /// Build a WebhookNotifier for each url of the Config, so that each url has its own
+    /// outbox entries and a retry only reaches the one which failed.
+    pub fn from_config(config: &Config) -> Vec<Self> {
+        config
+            .webhook_urls
+            .iter()
+            .map(|url| Self {
+                url: url.to_owned(),
+                // Built once for the lifetime of the program, the name identifies its outbox entries
+                name: Box::leak(format!("Webhook {}", url).into_boxed_str()),
+            })
+            .collect()
+    }
+
+    fn post(&self, payload: &str) -> Result<(), NotifierError> {
+        AGENT
+            .post(&self.url)
+            .set("Content-Type", "application/json")
+            .send_string(payload)
+            .map_err(|err| NotifierError(err.to_string()))?;
+        Ok(())
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Every url is allowed by the "webhook" channel of the Destination
+    fn accepts(&self, event: &Event) -> bool {
+        event.destination.allows("webhook")
+    }
+
+    fn notify(&self, _conn: &mut ConnType, event: &Event) -> Result<(), NotifierError> {
+        let payload = simd_json::to_string(&WebhookPayload {
+            version: WEBHOOK_VERSION,
+            kind: event.kind,
+            severity: Severity::from(event.incident.severity).to_string(),
+            alert: event.alert,
+            incident: event.incident,
+        })
+        .map_err(|err| NotifierError(format!("could not serialize the payload: {}", err)))?;
+
+        self.post(&payload)
+    }
+
+    fn notify_summary(&self, text: &str) -> Result<(), NotifierError> {
+        let payload = simd_json::to_string(&SummaryPayload {
+            version: WEBHOOK_VERSION,
+            kind: "summary",
+            text,
+        })
+        .map_err(|err| NotifierError(format!("could not serialize the payload: {}", err)))?;
+
+        self.post(&payload)
+    }
+}
@@ -1,9 +1,27 @@
+use chrono::{NaiveTime, Weekday};
+use chrono_tz::Tz;
 use clap::Parser;
 use config::ConfigError;
 use lettre::message::Mailbox;
+use regex::Regex;
 use serde::{de, Deserialize, Deserializer};
 
-use crate::Args;
+use crate::{
+    monitoring::{pending::PendingRule, recovery::RecoveryRule},
+    notifications::{
+        delivery::DeliveryWindow,
+        digest::DigestGroup,
+        dkim::{DkimAlgorithm, DkimKey},
+        escalation::EscalationPolicy,
+        i18n::{Locale, ReceiverSettings},
+        ratelimit::RateLimit,
+        routing::Route,
+        smtp::SmtpRelay,
+        tenants::TenantProfile,
+        transport::MailTransport,
+    },
+    Args,
+};
 
 #[derive(Debug, Deserialize, Clone)]
 
@@ -16,30 +34,141 @@ pub struct Config {
     #[serde(default = "default_maxconn")]
     pub database_max_connection: u32,
 
+    // MAIL TRANSPORT SETTINGS
+    #[serde(default)]
+    pub mail_transport: MailTransport,
+    pub sendmail_command: Option<String>,
+    pub mail_spool_dir: Option<String>,
+
     // SMTP SETTINGS
     #[serde(default = "default_smtp_port")]
     pub smtp_port: u16,
     #[serde(default = "default_smtp_tls")]
     pub smtp_tls: bool,
+    #[serde(default)]
     pub smtp_host: String,
+    #[serde(default)]
     pub smtp_user: String,
+    #[serde(default)]
     pub smtp_password: String,
     #[serde(deserialize_with = "mailbox_deser")]
     pub smtp_email_sender: Mailbox,
     #[serde(deserialize_with = "mailbox_deser")]
     pub smtp_email_receiver: Mailbox,
+    #[serde(default)]
+    pub smtp_relays: Vec<SmtpRelay>,
+    #[serde(default = "default_smtp_health_interval")]
+    pub smtp_health_interval: u64,
+
+    // DKIM SETTINGS
+    pub dkim_selector: Option<String>,
+    pub dkim_domain: Option<String>,
+    pub dkim_private_key_path: Option<String>,
+    #[serde(default)]
+    pub dkim_algorithm: DkimAlgorithm,
+    #[serde(default)]
+    pub dkim_keys: Vec<DkimKey>,
+
+    // TEMPLATES SETTINGS
+    pub templates_dir: Option<String>,
+
+    // LOCALIZATION SETTINGS
+    #[serde(default)]
+    pub locale: Locale,
+    #[serde(default = "default_timezone", deserialize_with = "tz_deser")]
+    pub timezone: Tz,
+
+    // SPARKLINE SETTINGS
+    #[serde(default = "default_sparkline_points")]
+    pub sparkline_points: usize,
+
+    // ACK SETTINGS
+    pub ack_listen: Option<String>,
+    pub ack_url: Option<String>,
+    pub ack_secret: Option<String>,
+    #[serde(default = "default_ack_link_validity")]
+    pub ack_link_validity: u64,
+
+    // DIGEST SETTINGS
+    pub digest_window: Option<u64>,
+    #[serde(default)]
+    pub digest_group_by: DigestGroup,
+
+    // OUTBOX SETTINGS
+    #[serde(default = "default_outbox_max_attempts")]
+    pub outbox_max_attempts: i32,
+
+    // RATE LIMIT SETTINGS
+    pub rate_limit_receiver: Option<RateLimit>,
+    pub rate_limit_channel: Option<RateLimit>,
+
+    // SLACK SETTINGS
+    pub slack_webhook_url: Option<String>,
+    pub slack_bot_token: Option<String>,
+    pub slack_channel: Option<String>,
+
+    // DISCORD SETTINGS
+    pub discord_webhook_url: Option<String>,
+
+    // MATRIX SETTINGS
+    pub matrix_homeserver: Option<String>,
+    pub matrix_access_token: Option<String>,
+    pub matrix_room_id: Option<String>,
+
+    // PAGERDUTY SETTINGS
+    pub pagerduty_routing_key: Option<String>,
+
+    // OPSGENIE SETTINGS
+    pub opsgenie_api_key: Option<String>,
+    #[serde(default = "default_opsgenie_api_url")]
+    pub opsgenie_api_url: String,
+
+    // WEBHOOK SETTINGS
+    #[serde(default)]
+    pub webhook_urls: Vec<String>,
+
+    // ROUTING SETTINGS
+    #[serde(default)]
+    pub routes: Vec<Route>,
+
+    // PENDING SETTINGS
+    #[serde(default)]
+    pub pending_rules: Vec<PendingRule>,
+
+    // RECOVERY SETTINGS
+    #[serde(default)]
+    pub recovery_rules: Vec<RecoveryRule>,
+
+    // DELIVERY SETTINGS
+    #[serde(default)]
+    pub delivery_windows: Vec<DeliveryWindow>,
+
+    // ESCALATION SETTINGS
+    #[serde(default)]
+    pub escalation_policies: Vec<EscalationPolicy>,
+
+    // TENANTS SETTINGS
+    #[serde(default)]
+    pub tenants: Vec<TenantProfile>,
+
+    // RECEIVERS SETTINGS
+    #[serde(default)]
+    pub receivers: Vec<ReceiverSettings>,
 
     pub cdc_adm: String,
 }
 
 impl Config {
     pub fn new() -> Result<Self, ConfigError> {
-        let args = Args::parse();
+        // The tests don't get the arguments of the program, they use the example config
+        let config_path = if cfg!(test) {
+            Some(concat!(env!("CARGO_MANIFEST_DIR"), "/alerts.example.config").to_owned())
+        } else {
+            Args::parse().config_path
+        };
 
         let config_builder = config::Config::builder().add_source(config::File::new(
-            &args
-                .config_path
-                .unwrap_or_else(|| "/etc/speculare/alerts.config".to_owned()),
+            &config_path.unwrap_or_else(|| "/etc/speculare/alerts.config".to_owned()),
             config::FileFormat::Toml,
         ));
 
@@ -47,19 +176,43 @@ impl Config {
     }
 }
 
-fn default_smtp_port() -> u16 {
+pub fn default_smtp_port() -> u16 {
     587
 }
 
-fn default_smtp_tls() -> bool {
+pub fn default_smtp_tls() -> bool {
     true
 }
 
+fn default_smtp_health_interval() -> u64 {
+    60
+}
+
+fn default_outbox_max_attempts() -> i32 {
+    8
+}
+
+fn default_opsgenie_api_url() -> String {
+    "https://api.opsgenie.com".to_owned()
+}
+
+fn default_sparkline_points() -> usize {
+    30
+}
+
+fn default_ack_link_validity() -> u64 {
+    7 * 24 * 3600
+}
+
+fn default_timezone() -> Tz {
+    Tz::UTC
+}
+
 fn default_maxconn() -> u32 {
     10
 }
 
-fn mailbox_deser<'de, D>(data: D) -> Result<Mailbox, D::Error>
+pub fn mailbox_deser<'de, D>(data: D) -> Result<Mailbox, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -73,3 +226,77 @@ where
     }
     .map_err(de::Error::custom)
 }
+
+pub fn opt_mailbox_deser<'de, D>(data: D) -> Result<Option<Mailbox>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    mailbox_deser(data).map(Some)
+}
+
+pub fn mailboxes_deser<'de, D>(data: D) -> Result<Vec<Mailbox>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let list: Vec<String> = de::Deserialize::deserialize(data)?;
+    list.iter()
+        .map(|s| {
+            s.parse()
+                .map_err(|e| de::Error::custom(format!("Mailbox error for \"{}\": {}", s, e)))
+        })
+        .collect()
+}
+
+pub fn regex_deser<'de, D>(data: D) -> Result<Option<Regex>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = de::Deserialize::deserialize(data)?;
+    // Anchor the pattern so that "disk" does not match "disk_io"
+    match Regex::new(&format!("^(?:{})$", s)) {
+        Ok(re) => Ok(Some(re)),
+        Err(e) => Err(de::Error::custom(format!(
+            "Regex error for \"{}\": {}",
+            s, e
+        ))),
+    }
+}
+
+pub fn time_deser<'de, D>(data: D) -> Result<NaiveTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = de::Deserialize::deserialize(data)?;
+    NaiveTime::parse_from_str(&s, "%H:%M")
+        .map_err(|e| de::Error::custom(format!("Time error for \"{}\" (HH:MM): {}", s, e)))
+}
+
+pub fn weekdays_deser<'de, D>(data: D) -> Result<Option<Vec<Weekday>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let list: Vec<String> = de::Deserialize::deserialize(data)?;
+    list.iter()
+        .map(|s| {
+            s.parse()
+                .map_err(|e| de::Error::custom(format!("Weekday error for \"{}\": {}", s, e)))
+        })
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+pub fn tz_deser<'de, D>(data: D) -> Result<Tz, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = de::Deserialize::deserialize(data)?;
+    s.parse()
+        .map_err(|e| de::Error::custom(format!("Timezone error for \"{}\": {}", s, e)))
+}
+
+pub fn opt_tz_deser<'de, D>(data: D) -> Result<Option<Tz>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    tz_deser(data).map(Some)
+}
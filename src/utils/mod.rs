@@ -1,4 +1,10 @@
+use std::time::Duration;
+
+use bastion::context::BastionContext;
 use serde::{Deserialize, Serialize};
+use tokio::time::{interval_at, Instant};
+
+use crate::SUPERVISOR;
 
 pub mod config;
 pub mod impls;
@@ -34,3 +40,29 @@ pub struct CdcChange {
     pub kind: CdcKind,
     table: String,
 }
+
+/// Run the function every period in a children supervised by Bastion, the first
+/// run happening after one period (what's needed at boot being done by the caller).
+///
+/// The name of the worker is only used in the logs.
+pub fn spawn_periodic<F>(name: &'static str, period: Duration, f: F)
+where
+    F: Fn() + Clone + Send + Sync + 'static,
+{
+    SUPERVISOR
+        .children(|child| {
+            child.with_exec(move |_ctx: BastionContext| {
+                let f = f.clone();
+                async move {
+                    let mut interval = interval_at(Instant::now() + period, period);
+
+                    loop {
+                        interval.tick().await;
+                        f();
+                    }
+                }
+            })
+        })
+        .expect("Cannot create the Children for Bastion");
+    trace!("{}: worker started, running every {:?}", name, period);
+}